@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Run with `cargo +nightly fuzz run round_trip` from the repository root
+fuzz_target!(|text: &str| {
+	huffman::assert_round_trip(text);
+});
@@ -0,0 +1,175 @@
+use anyhow::{Result, Context};
+use std::{fs, path::Path};
+use crate::{entropy, get_letter_frequencies};
+use crate::container::{compress_counting_bits, decompress};
+
+#[derive(Clone, Debug, PartialEq)]
+/// Sizes measured while compressing a file
+///
+/// Besides the real sizes, this compares the Huffman encoding against a plain
+/// 8 bits per character and against the entropy lower bound, showing both the
+/// practical and the theoretical savings. With the `flate2` feature it also
+/// measures the input under gzip, to compare against a production compressor.
+pub struct CompressionStats {
+	pub input_bytes: usize,
+	pub output_bytes: usize,
+	pub symbols: usize,
+	/// Bits used by a plain 8 bit per character encoding
+	pub baseline_bits: usize,
+	/// The entropy lower bound on bits for any prefix code
+	pub entropy_bits: f64,
+	/// Bits used by the Huffman encoding, not counting the container
+	pub huffman_bits: usize,
+	/// The size of the input compressed with gzip at its default level
+	#[cfg(feature = "flate2")]
+	pub gzip_bytes: usize,
+}
+
+impl CompressionStats {
+	/// Measures the sizes for a text and its encoding
+	///
+	/// * `text` - The original text
+	/// * `huffman_bits` - The number of bits the text encoded to
+	/// * `output_bytes` - The size of the written output
+	pub fn new(text: &str, huffman_bits: usize, output_bytes: usize) -> Self {
+		let frequencies = get_letter_frequencies(text);
+		let symbols = text.chars().count();
+
+		CompressionStats {
+			input_bytes: text.len(),
+			output_bytes,
+			symbols,
+			baseline_bits: symbols * 8,
+			entropy_bits: entropy(&frequencies) * symbols as f64,
+			huffman_bits,
+			#[cfg(feature = "flate2")]
+			gzip_bytes: gzip_len(text),
+		}
+	}
+
+	/// Gets how many bits the Huffman encoding saves over 8 bits per character
+	pub fn bits_saved_vs_baseline(&self) -> i64 {
+		self.baseline_bits as i64 - self.huffman_bits as i64
+	}
+
+	/// Gets how many bits the Huffman encoding spends over the entropy bound
+	pub fn bits_over_entropy(&self) -> f64 {
+		self.huffman_bits as f64 - self.entropy_bits
+	}
+
+	/// Gets the compressed size as a fraction of the original size
+	pub fn ratio(&self) -> f64 {
+		if self.input_bytes == 0 {
+			return 0.0;
+		}
+
+		self.output_bytes as f64 / self.input_bytes as f64
+	}
+
+	/// Gets the compressed size as a fraction of the size under gzip
+	///
+	/// Above 1 means gzip compresses the input better. Gzip also codes repeated
+	/// strings, not just single characters, so it usually wins on all but
+	/// short or random text. Gzip output always has a header, so this is
+	/// defined even for empty input.
+	#[cfg(feature = "flate2")]
+	pub fn ratio_vs_gzip(&self) -> f64 {
+		self.output_bytes as f64 / self.gzip_bytes as f64
+	}
+}
+
+/// Measures the size of text compressed with gzip at its default level
+#[cfg(feature = "flate2")]
+fn gzip_len(text: &str) -> usize {
+	use flate2::{Compression, write::GzEncoder};
+	use std::io::Write;
+
+	let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+	encoder.write_all(text.as_bytes())
+		.and_then(|_| encoder.finish())
+		.map(|compressed| compressed.len())
+		.expect("Writing to a Vec cannot fail")
+}
+
+/// Compresses a text file into a container file
+///
+/// This is the pipeline entry for binaries. It trains a tree on the input,
+/// encodes the input with it, and writes the container made by `compress` to
+/// the output. Every step returns an error instead of panicking.
+pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(input_path: P, output_path: Q) -> Result<CompressionStats> {
+	let text = fs::read_to_string(input_path)
+		.context("Failed to read input file")?;
+	let (bytes, huffman_bits) = compress_counting_bits(&text)
+		.context("Failed to compress input")?;
+
+	fs::write(output_path, &bytes)
+		.context("Failed to write output file")?;
+
+	Ok(CompressionStats::new(&text, huffman_bits, bytes.len()))
+}
+
+/// Compresses a text file into a container file, the reverse of
+/// `decompress_file`
+///
+/// This is `run` under a name that pairs with `decompress_file`.
+pub fn compress_file<P: AsRef<Path>, Q: AsRef<Path>>(input_path: P, output_path: Q) -> Result<CompressionStats> {
+	run(input_path, output_path)
+}
+
+/// Decompresses a container file back into a text file
+///
+/// With `verify_checksum` set, this fails if the decoded text does not match
+/// the checksum stored when it was compressed.
+pub fn decompress_file<P: AsRef<Path>, Q: AsRef<Path>>(input_path: P, output_path: Q, verify_checksum: bool) -> Result<()> {
+	let data = fs::read(input_path)
+		.context("Failed to read input file")?;
+	let text = decompress(&data, verify_checksum)?;
+
+	fs::write(output_path, text)
+		.context("Failed to write output file")
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+	#[allow(unused)]
+	use super::CompressionStats;
+
+	// Compress a file to a container file and back
+	#[bench]
+	fn run(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let compressed = std::env::temp_dir().join("huffman-run.huff");
+		let decompressed = std::env::temp_dir().join("huffman-run.txt");
+
+		let stats = super::run("2022_fall-eecs660-pa2-input.txt", &compressed).expect("Failed to compress file");
+		assert_eq!(stats.output_bytes as u64, std::fs::metadata(&compressed).expect("Failed to read metadata").len());
+		assert_eq!(stats.input_bytes, text.len());
+
+		super::decompress_file(&compressed, &decompressed, true).expect("Failed to decompress file");
+		assert_eq!(std::fs::read_to_string(&decompressed).expect("Failed to read file"), text);
+		assert!(super::run("missing-input.txt", &compressed).is_err());
+
+		b.bytes = text.len() as u64;
+		b.iter(|| super::run("2022_fall-eecs660-pa2-input.txt", &compressed));
+	}
+
+	// Compare a compressed file against gzip
+	#[cfg(feature = "flate2")]
+	#[bench]
+	fn gzip_len(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let (container, huffman_bits) = crate::container::compress_counting_bits(&text)
+			.expect("Failed to compress message");
+		let stats = CompressionStats::new(&text, huffman_bits, container.len());
+
+		assert!(stats.gzip_bytes < text.len());
+		assert_eq!(stats.ratio_vs_gzip(), container.len() as f64 / stats.gzip_bytes as f64);
+		assert_eq!(CompressionStats::new("", 0, 0).ratio_vs_gzip(), 0.0);
+
+		b.bytes = text.len() as u64;
+		b.iter(|| super::gzip_len(&text));
+	}
+}
@@ -0,0 +1,189 @@
+use anyhow::{anyhow, Result, Context};
+use crate::{Bits, HuffmanTree};
+use crate::crc32::crc32;
+
+/// Marks the start of a compressed container
+const MAGIC: &[u8; 4] = b"HUFF";
+
+/// Bits a container spends on its count of symbols
+pub(crate) const SYMBOL_COUNT_BITS: usize = 32;
+
+/// Bytes a container spends on each symbol, a `u32` codepoint and `u8` length
+const SYMBOL_ENTRY_BYTES: usize = 4 + 1;
+
+/// Bits a container spends on each symbol
+pub(crate) const SYMBOL_ENTRY_BITS: usize = SYMBOL_ENTRY_BYTES * 8;
+
+/// Compresses text into a self-contained byte container
+///
+/// The container holds, in order and with little-endian integers:
+///
+/// * The 4 byte magic `HUFF`
+/// * A `u32` CRC-32 of the original text's UTF-8 bytes
+/// * A `u32` count of symbols, then each symbol as a `u32` codepoint and a
+///   `u8` code length
+/// * A `u8` count of padding bits in the final byte, then the encoded bits
+///   packed MSB-first
+///
+/// The text is encoded with canonical codes, so the code lengths are enough
+/// for `decompress` to rebuild the tree.
+pub fn compress(text: &str) -> Result<Vec<u8>> {
+	let (output, _) = compress_counting_bits(text)?;
+	Ok(output)
+}
+
+/// Compresses text into a container, also returning the number of encoded bits
+pub(crate) fn compress_counting_bits(text: &str) -> Result<(Vec<u8>, usize)> {
+	let mut output = Vec::new();
+	output.extend_from_slice(MAGIC);
+	output.extend_from_slice(&crc32(text.as_bytes()).to_le_bytes());
+
+	if text.is_empty() {
+		output.extend_from_slice(&0u32.to_le_bytes());
+		output.push(0);
+		return Ok((output, 0));
+	}
+
+	let lengths = HuffmanTree::new(text)?.code_lengths();
+	let tree = HuffmanTree::from_code_lengths(&lengths)?;
+	let bits = tree.encode(text)?;
+
+	output.extend_from_slice(&(lengths.len() as u32).to_le_bytes());
+	for (character, length) in lengths {
+		let length = u8::try_from(length)
+			.context("Code is too long to store in a container")?;
+
+		output.extend_from_slice(&u32::from(character).to_le_bytes());
+		output.push(length);
+	}
+
+	output.push(bits.pad_bits() as u8);
+	output.extend_from_slice(&bits.to_bytes());
+
+	Ok((output, bits.len()))
+}
+
+/// Decompresses a container made by `compress` back into text
+///
+/// With `verify_checksum` set, the decoded text is checked against the stored
+/// CRC-32, which catches corrupted payloads and mismatched trees. Skipping it
+/// saves a pass over the output.
+pub fn decompress(data: &[u8], verify_checksum: bool) -> Result<String> {
+	let mut reader = Reader {data, position: 0};
+
+	if reader.read_bytes(MAGIC.len())? != MAGIC {
+		return Err(anyhow!("Data is not a Huffman container"));
+	}
+
+	let checksum = reader.read_u32()?;
+	let symbols = reader.read_u32()? as usize;
+	if symbols > reader.rest().len() / SYMBOL_ENTRY_BYTES {
+		return Err(anyhow!("Container claims more symbols than it holds"));
+	}
+
+	let mut lengths = Vec::with_capacity(symbols);
+	for _ in 0..symbols {
+		let character = char::from_u32(reader.read_u32()?)
+			.context("Container holds an invalid symbol")?;
+		let length = reader.read_bytes(1)?[0] as usize;
+
+		lengths.push((character, length));
+	}
+
+	let pad_bits = reader.read_bytes(1)?[0] as usize;
+	let payload = reader.rest();
+	if pad_bits > 7 || (pad_bits > 0 && payload.is_empty()) {
+		return Err(anyhow!("Container has an invalid padding length"));
+	}
+
+	let num_bits = payload.len() * 8 - pad_bits;
+	let bits = Bits::from_bytes(payload, num_bits)?;
+
+	let text = match lengths.is_empty() {
+		true if num_bits == 0 => String::new(),
+		true => return Err(anyhow!("Container has bits but no symbols")),
+		false => HuffmanTree::from_code_lengths(&lengths)?.decode(&bits)?
+	};
+
+	if verify_checksum && crc32(text.as_bytes()) != checksum {
+		return Err(anyhow!("Checksum does not match decoded text"));
+	}
+
+	Ok(text)
+}
+
+/// Reads fields from the front of a container
+struct Reader<'a> {
+	data: &'a [u8],
+	position: usize,
+}
+
+impl<'a> Reader<'a> {
+	/// Reads a fixed number of bytes
+	fn read_bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+		let end = self.position + count;
+		let bytes = self.data.get(self.position..end)
+			.context("Container ends unexpectedly")?;
+
+		self.position = end;
+		Ok(bytes)
+	}
+
+	/// Reads a little-endian u32
+	fn read_u32(&mut self) -> Result<u32> {
+		let bytes = self.read_bytes(4)?;
+		Ok(u32::from_le_bytes(bytes.try_into()?))
+	}
+
+	/// Gets every byte that has not been read yet
+	fn rest(&self) -> &'a [u8] {
+		&self.data[self.position..]
+	}
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+
+	// Compress the input file
+	#[bench]
+	fn compress(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+
+		b.bytes = text.len() as u64;
+		b.iter(|| super::compress(&text));
+	}
+
+	// Decompress the input file
+	#[bench]
+	fn decompress(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let data = super::compress(&text)
+			.expect("Failed to compress");
+
+		assert_eq!(super::decompress(&data, true).expect("Failed to decompress"), text);
+		b.bytes = text.len() as u64;
+		b.iter(|| super::decompress(&data, true));
+	}
+
+	// Reject a symbol count larger than the container
+	#[bench]
+	fn decompress_symbol_count(b: &mut Bencher) {
+		let mut data = super::MAGIC.to_vec();
+		data.extend_from_slice(&0u32.to_le_bytes());
+		data.extend_from_slice(&u32::MAX.to_le_bytes());
+
+		let error = super::decompress(&data, true).expect_err("Decompressed a truncated codebook");
+		assert_eq!(error.to_string(), "Container claims more symbols than it holds");
+
+		let valid = super::compress("ab").expect("Failed to compress");
+		let mut overclaimed = valid.clone();
+		overclaimed[8..12].copy_from_slice(&3u32.to_le_bytes());
+		assert!(super::decompress(&overclaimed, true).is_err());
+		assert_eq!(super::decompress(&valid, true).expect("Failed to decompress"), "ab");
+
+		b.iter(|| super::decompress(&data, true));
+	}
+}
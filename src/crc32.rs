@@ -0,0 +1,56 @@
+/// Reversed form of the IEEE CRC-32 polynomial
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// Lookup table for each possible low byte of the running CRC
+const TABLE: [u32; 256] = build_table();
+
+/// Builds the byte-at-a-time lookup table at compile time
+const fn build_table() -> [u32; 256] {
+	let mut table = [0; 256];
+	let mut index = 0;
+
+	while index < 256 {
+		let mut crc = index as u32;
+		let mut bit = 0;
+
+		while bit < 8 {
+			crc = match crc & 1 {
+				1 => (crc >> 1) ^ POLYNOMIAL,
+				_ => crc >> 1
+			};
+			bit += 1;
+		}
+
+		table[index] = crc;
+		index += 1;
+	}
+
+	table
+}
+
+/// Computes the IEEE CRC-32 checksum of some bytes
+///
+/// This is the same checksum used by gzip, zip and PNG.
+pub fn crc32(data: &[u8]) -> u32 {
+	let crc = data.iter().fold(u32::MAX, |crc, byte| {
+		let index = ((crc ^ u32::from(*byte)) & 0xFF) as usize;
+		(crc >> 8) ^ TABLE[index]
+	});
+
+	!crc
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+
+	// Checksum the input file
+	#[bench]
+	fn crc32(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+
+		assert_eq!(super::crc32(b"123456789"), 0xCBF4_3926);
+		b.iter(|| super::crc32(text.as_bytes()));
+	}
+}
@@ -0,0 +1,19 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Which bit is written when following each branch of a Huffman tree
+///
+/// Textbooks and other tools do not agree on this, so matching their output
+/// may need the opposite of the default.
+pub enum BitConvention {
+	/// Left branches are `0` and right branches are `1`
+	#[default]
+	LeftZero,
+	/// Left branches are `1` and right branches are `0`
+	LeftOne,
+}
+
+impl BitConvention {
+	/// Gets the bit written when following a left branch
+	pub fn left_bit(self) -> bool {
+		self == BitConvention::LeftOne
+	}
+}
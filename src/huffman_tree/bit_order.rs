@@ -0,0 +1,24 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// The order bits are packed into each byte
+///
+/// External formats disagree on this. DEFLATE, for one, fills each byte from
+/// its least significant bit.
+pub enum BitOrder {
+	/// The first bit goes in the most significant bit of each byte
+	#[default]
+	MsbFirst,
+	/// The first bit goes in the least significant bit of each byte
+	LsbFirst,
+}
+
+impl BitOrder {
+	/// Gets the mask for a bit at a position within a byte
+	///
+	/// * `index` - The position of the bit in the byte, from 0 to 7
+	pub fn mask(self, index: usize) -> u8 {
+		match self {
+			BitOrder::MsbFirst => 0x80 >> index,
+			BitOrder::LsbFirst => 0x01 << index,
+		}
+	}
+}
@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Result, Context};
+use std::io::{ErrorKind, Read};
+
+/// The number of bytes read from the underlying reader at once
+const BUFFER_BYTES: usize = 4096;
+
+/// Reads bits one at a time from bytes packed most significant bit first
+///
+/// This is the reading side of `BitWriter`. Without a length it yields every
+/// bit of every byte, including any padding at the end. With a length it stops
+/// after that many bits, dropping the padding in the final byte.
+pub struct BitReader<R: Read> {
+	reader: R,
+	bytes: Vec<u8>,
+	position: usize,
+	current: u8,
+	remaining: u8,
+	bits_left: Option<usize>,
+}
+
+impl<R: Read> BitReader<R> {
+	/// Constructs a bit reader that reads until the underlying reader ends
+	///
+	/// * `reader` - Where packed bytes are read from
+	pub fn new(reader: R) -> Self {
+		BitReader {
+			reader,
+			bytes: Vec::new(),
+			position: 0,
+			current: 0,
+			remaining: 0,
+			bits_left: None
+		}
+	}
+
+	/// Constructs a bit reader that reads exactly `num_bits` bits
+	///
+	/// * `reader` - Where packed bytes are read from
+	/// * `num_bits` - The number of real bits, not counting padding
+	pub fn with_len(reader: R, num_bits: usize) -> Self {
+		BitReader {bits_left: Some(num_bits), ..Self::new(reader)}
+	}
+
+	/// Reads the next bit, or None once every bit has been read
+	///
+	/// This will error if the underlying reader fails, or if it ends before the
+	/// length given to `with_len`.
+	pub fn read_bit(&mut self) -> Result<Option<bool>> {
+		if self.bits_left == Some(0) {
+			return Ok(None);
+		}
+
+		if self.remaining == 0 {
+			match self.next_byte()? {
+				Some(byte) => {
+					self.current = byte;
+					self.remaining = 8;
+				}
+				None if self.bits_left.is_some() => {
+					return Err(anyhow!("Input ended before every bit was read"));
+				}
+				None => return Ok(None)
+			}
+		}
+
+		let bit = self.current & 0x80 != 0;
+		self.current <<= 1;
+		self.remaining -= 1;
+
+		if let Some(bits_left) = self.bits_left.as_mut() {
+			*bits_left -= 1;
+		}
+
+		Ok(Some(bit))
+	}
+
+	/// Gets the next byte from the buffer, refilling it when empty
+	fn next_byte(&mut self) -> Result<Option<u8>> {
+		if self.position == self.bytes.len() {
+			self.bytes.resize(BUFFER_BYTES, 0);
+
+			let count = loop {
+				match self.reader.read(&mut self.bytes) {
+					Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+					result => break result.context("Failed to read packed bits")?
+				}
+			};
+
+			self.bytes.truncate(count);
+			self.position = 0;
+		}
+
+		let byte = self.bytes.get(self.position).copied();
+		self.position += 1;
+
+		Ok(byte)
+	}
+}
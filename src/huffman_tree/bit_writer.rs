@@ -0,0 +1,82 @@
+use anyhow::{Result, Context};
+use std::io::Write;
+use super::bits::Bits;
+
+/// The number of packed bytes held before they are written out
+const BUFFER_BYTES: usize = 4096;
+
+/// Packs bits into bytes and writes them to an underlying writer
+///
+/// Bits are packed most significant bit first, matching `Bits::to_bytes`.
+/// Full bytes are buffered and written in blocks, and `finish` writes the
+/// final partial byte padded with zeros.
+pub struct BitWriter<W: Write> {
+	writer: W,
+	bytes: Vec<u8>,
+	current: u8,
+	filled: u8,
+	total_bits: usize,
+}
+
+impl<W: Write> BitWriter<W> {
+	/// Constructs a new bit writer
+	///
+	/// * `writer` - Where packed bytes are written
+	pub fn new(writer: W) -> Self {
+		BitWriter {
+			writer,
+			bytes: Vec::with_capacity(BUFFER_BYTES),
+			current: 0,
+			filled: 0,
+			total_bits: 0
+		}
+	}
+
+	/// Writes a single bit
+	pub fn write_bit(&mut self, bit: bool) -> Result<()> {
+		self.current |= u8::from(bit) << (7 - self.filled);
+		self.filled += 1;
+		self.total_bits += 1;
+
+		if self.filled == 8 {
+			self.bytes.push(self.current);
+			self.current = 0;
+			self.filled = 0;
+
+			if self.bytes.len() == BUFFER_BYTES {
+				self.flush_bytes()?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Writes every bit in a Bits object, in order
+	pub fn write_bits(&mut self, bits: &Bits) -> Result<()> {
+		bits.iter().try_for_each(|bit| self.write_bit(bit))
+	}
+
+	/// Writes the final padded byte and flushes the underlying writer
+	///
+	/// Returns the total number of bits written, not counting padding.
+	pub fn finish(mut self) -> Result<usize> {
+		if self.filled > 0 {
+			self.bytes.push(self.current);
+		}
+
+		self.flush_bytes()?;
+		self.writer.flush()
+			.context("Failed to flush bit writer")?;
+
+		Ok(self.total_bits)
+	}
+
+	/// Writes out every buffered full byte
+	fn flush_bytes(&mut self) -> Result<()> {
+		self.writer.write_all(&self.bytes)
+			.context("Failed to write packed bits")?;
+		self.bytes.clear();
+
+		Ok(())
+	}
+}
@@ -1,4 +1,8 @@
-#[derive(Clone)]
+use anyhow::{anyhow, Result, Context};
+use std::ops::Range;
+use super::bit_order::BitOrder;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
 /// A collection of individual bits
 pub struct Bits {
 	collection: Vec<bool>
@@ -10,6 +14,40 @@ impl Bits {
 		Bits {collection: Vec::new()}
 	}
 
+	/// Unpacks bits from bytes packed most significant bit first
+	///
+	/// Only the first `len` bits are kept, so padding in the final byte is
+	/// dropped. This will error if the bytes hold fewer than `len` bits.
+	pub fn from_bytes(bytes: &[u8], len: usize) -> Result<Self> {
+		Self::from_bytes_ordered(bytes, len, BitOrder::MsbFirst)
+	}
+
+	/// Unpacks bits from bytes packed in either bit order
+	///
+	/// This reverses `to_bytes_ordered` with the same order. Only the first
+	/// `len` bits are kept, and this will error if the bytes hold fewer.
+	pub fn from_bytes_ordered(bytes: &[u8], len: usize, order: BitOrder) -> Result<Self> {
+		if len > bytes.len() * 8 {
+			return Err(anyhow!("Not enough bytes to hold {len} bits"));
+		}
+
+		let collection = (0..len)
+			.map(|index| bytes[index / 8] & order.mask(index % 8) != 0)
+			.collect();
+
+		Ok(Bits {collection})
+	}
+
+	/// Copies bits from a slice of booleans
+	pub fn from_bool_slice(bits: &[bool]) -> Self {
+		Bits {collection: bits.to_vec()}
+	}
+
+	/// Gets the bits as a slice of booleans
+	pub fn as_slice(&self) -> &[bool] {
+		&self.collection
+	}
+
 	/// Adds a single bit to the end of this collection
 	pub fn add(&mut self, bit: bool) {
 		self.collection.push(bit);
@@ -22,27 +60,259 @@ impl Bits {
 		}
 	}
 
+	/// Inserts a bit at an index, shifting every later bit along by one
+	///
+	/// An index equal to the length adds the bit to the end, like `add`. This
+	/// will error if the index is past the end.
+	pub fn insert(&mut self, index: usize, bit: bool) -> Result<()> {
+		if index > self.len() {
+			return Err(anyhow!("Index {index} is past the end of {} bits", self.len()));
+		}
+
+		self.collection.insert(index, bit);
+		Ok(())
+	}
+
+	/// Replaces a range of bits with another Bits object
+	///
+	/// The replacement may be longer or shorter than the range, so this can
+	/// insert, remove or patch bits. A range starting at the length adds the
+	/// replacement to the end. This will error if the range is backwards or
+	/// ends past the end.
+	pub fn splice(&mut self, range: Range<usize>, replacement: &Self) -> Result<()> {
+		if range.start > range.end || range.end > self.len() {
+			return Err(anyhow!("Range {range:?} is out of bounds for {} bits", self.len()));
+		}
+
+		self.collection.splice(range, replacement.collection.iter().copied());
+		Ok(())
+	}
+
+	/// Removes every bit while keeping the allocated capacity
+	///
+	/// This lets one buffer be reused across many encodes.
+	pub fn clear(&mut self) {
+		self.collection.clear();
+	}
+
+	/// Reverses the order of the bits in place
+	///
+	/// This converts between most and least significant bit first orders,
+	/// like when reading a code from an LSB-first format.
+	pub fn reverse(&mut self) {
+		self.collection.reverse();
+	}
+
+	/// Reserves space for at least `additional` more bits
+	pub fn reserve(&mut self, additional: usize) {
+		self.collection.reserve(additional);
+	}
+
+	/// Get the number of bits the collection can hold without reallocating
+	pub fn capacity(&self) -> usize {
+		self.collection.capacity()
+	}
+
+	/// Appends bits written as a string of `0`s and `1`s
+	///
+	/// The whole string is checked before anything is appended, so on an
+	/// invalid character this errors and leaves the collection unchanged.
+	pub fn extend_from_str(&mut self, string: &str) -> Result<()> {
+		if let Some(invalid) = string.chars().find(|c| *c != '0' && *c != '1') {
+			return Err(anyhow!("Invalid bit character {invalid:?}"));
+		}
+
+		self.collection.extend(string.chars().map(|c| c == '1'));
+		Ok(())
+	}
+
 	/// Get the number of bits in the collection
 	pub fn len(&self) -> usize {
 		self.collection.len()
 	}
+
+	/// Counts the bits that are set
+	///
+	/// The balance of ones and zeros in encoded output is a quick check of how
+	/// close it is to random.
+	pub fn count_ones(&self) -> usize {
+		self.collection.iter()
+			.filter(|bit| **bit)
+			.count()
+	}
+
+	/// Counts the bits that are not set
+	pub fn count_zeros(&self) -> usize {
+		self.len() - self.count_ones()
+	}
+
+	/// Gets the bit at an index, or None if the index is out of bounds
+	pub fn get(&self, index: usize) -> Option<bool> {
+		self.collection.get(index).copied()
+	}
+
+	/// Iterates over the bits in order
+	pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+		self.collection.iter().copied()
+	}
+
+	/// Iterates over the bits in groups of `size`, in order
+	///
+	/// Every group has `size` bits except the last, which has whatever is left
+	/// when the length is not a multiple of `size`. Groups of 8 line up with
+	/// the bytes of `to_bytes`. This panics if `size` is 0, like
+	/// `slice::chunks`.
+	pub fn chunks(&self, size: usize) -> impl Iterator<Item = &[bool]> + '_ {
+		self.collection.chunks(size)
+	}
+
+	/// Gets the number of zero bits `to_bytes` pads the final byte with
+	///
+	/// This is between 0 and 7. Storing it alongside the bytes tells a reader
+	/// exactly how many real bits the last byte holds.
+	pub fn pad_bits(&self) -> usize {
+		(8 - self.len() % 8) % 8
+	}
+
+	/// Packs the bits into bytes, most significant bit first
+	///
+	/// The final byte is padded with zeros when the number of bits is not a
+	/// multiple of 8.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		self.to_bytes_ordered(BitOrder::MsbFirst)
+	}
+
+	/// Packs the bits into bytes in either bit order
+	///
+	/// The unused bits of the final byte are zeros, at its low end for
+	/// `MsbFirst` and its high end for `LsbFirst`.
+	pub fn to_bytes_ordered(&self, order: BitOrder) -> Vec<u8> {
+		self.collection.chunks(8).map(|chunk| {
+			chunk.iter().enumerate().fold(0, |byte, (index, bit)| {
+				if *bit {byte | order.mask(index)} else {byte}
+			})
+		}).collect()
+	}
+
+	/// Writes the bits as `0`s and `1`s with a separator every `group` bits
+	///
+	/// Grouping by 8 shows byte boundaries, like `10110010 0110`. A trailing
+	/// group shorter than `group` is kept as is. A group of 0 adds no
+	/// separators, matching `to_string`.
+	pub fn to_string_grouped(&self, group: usize, separator: char) -> String {
+		if group == 0 {
+			return self.to_string();
+		}
+
+		let mut string = String::with_capacity(self.len() + self.len() / group * separator.len_utf8());
+		for (index, bit) in self.collection.iter().enumerate() {
+			if index > 0 && index % group == 0 {
+				string.push(separator);
+			}
+			string.push(if *bit {'1'} else {'0'});
+		}
+
+		string
+	}
+
+	/// Adds a value as a `width` wide field, most significant bit first
+	///
+	/// Widths past the size of a usize are padded with leading zeros. This will
+	/// error if the value does not fit in `width` bits.
+	pub fn add_field(&mut self, value: usize, width: usize) -> Result<()> {
+		if width < usize::BITS as usize && value >> width != 0 {
+			return Err(anyhow!("Value {value} does not fit in {width} bits"));
+		}
+
+		for index in (0..width).rev() {
+			self.add(index < usize::BITS as usize && (value >> index) & 1 == 1);
+		}
+
+		Ok(())
+	}
+
+	/// Gets the `width` wide field starting at `position`, as written by
+	/// `add_field`
+	///
+	/// This will error if the bits end before the field, or if the value is too
+	/// large for this platform.
+	pub fn get_field(&self, position: usize, width: usize) -> Result<usize> {
+		let field = position.checked_add(width)
+			.and_then(|end| self.collection.get(position..end))
+			.ok_or_else(|| anyhow!("Bits end before the {width} bit field at {position}"))?;
+
+		field.iter().try_fold(0usize, |value, bit| {
+			value.checked_mul(2).map(|value| value | usize::from(*bit))
+		}).ok_or_else(|| anyhow!("Field at {position} is too large for this platform"))
+	}
+
+	/// Joins this collection and another into one, prefixed by this one's length
+	///
+	/// The length is written first as a `prefix_bits` wide field, most
+	/// significant bit first, followed by these bits and then `other`. This
+	/// lets sections like a serialized tree and its payload share one stream.
+	/// This will error if the length does not fit in `prefix_bits`.
+	pub fn concat_with_length_prefix(&self, other: &Self, prefix_bits: usize) -> Result<Self> {
+		let mut joined = Bits::new();
+		joined.reserve(prefix_bits + self.len() + other.len());
+		joined.add_field(self.len(), prefix_bits)?;
+		joined.append(self);
+		joined.append(other);
+
+		Ok(joined)
+	}
+
+	/// Splits a collection made by `concat_with_length_prefix` back into its parts
+	///
+	/// This will error if the bits end before the prefix or the section it
+	/// describes, or if the length is too large for this platform.
+	pub fn split_length_prefixed(&self, prefix_bits: usize) -> Result<(Self, Self)> {
+		if self.len() < prefix_bits {
+			return Err(anyhow!("Bits end before the {prefix_bits} bit length prefix"));
+		}
+
+		let len = self.get_field(0, prefix_bits)
+			.context("Length prefix is too large")?;
+		let rest = &self.collection[prefix_bits..];
+
+		if rest.len() < len {
+			return Err(anyhow!("Bits end before the {len} bit section"));
+		}
+
+		let (first, second) = rest.split_at(len);
+		Ok((Bits {collection: first.to_vec()}, Bits {collection: second.to_vec()}))
+	}
+}
+
+impl std::str::FromStr for Bits {
+	type Err = anyhow::Error;
+
+	/// Parses a string of `0`s and `1`s
+	fn from_str(string: &str) -> Result<Self> {
+		let mut bits = Bits::new();
+		bits.extend_from_str(string)?;
+
+		Ok(bits)
+	}
 }
 
 impl std::fmt::Debug for Bits {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		write!(f, "{}", self.to_string())
+		write!(f, "{self}")
 	}
 }
 
-impl std::string::ToString for Bits {
-    fn to_string(&self) -> String {
-		self.collection.iter().map(|e| {
+impl std::fmt::Display for Bits {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let string: String = self.collection.iter().map(|e| {
 			match e {
-				false => "0",
-				true => "1"
+				false => '0',
+				true => '1'
 			}
-		}).collect()
-    }
+		}).collect();
+
+		write!(f, "{string}")
+	}
 }
 
 mod benchmarks {
@@ -50,9 +320,12 @@ mod benchmarks {
 	use test::Bencher;
 	#[allow(unused)]
 	use test::black_box;
+	#[allow(unused)]
+	use super::BitOrder;
 
 	// Append bits
 	#[bench]
+	#[allow(clippy::unnecessary_mut_passed)]
 	fn bits_append(b: &mut Bencher) {
 		let mut bits = super::Bits::new();
 		bits.add(true);
@@ -146,6 +419,240 @@ mod benchmarks {
 		b.iter(|| bits.append(black_box(&mut other)));
 	}
 
+	// Pack bits into bytes
+	#[bench]
+	fn bits_to_bytes(b: &mut Bencher) {
+		let mut bits = super::Bits::new();
+		for index in 0..10_000 {
+			bits.add(index % 3 == 0);
+		}
+
+		b.iter(|| black_box(&bits).to_bytes());
+	}
+
+	// Write and read fixed width fields
+	#[bench]
+	fn bits_add_field(b: &mut Bencher) {
+		let mut bits = super::Bits::new();
+		bits.add_field(5, 4).expect("Failed to add field");
+		bits.add_field(1, 70).expect("Failed to add field");
+		assert_eq!(bits.len(), 74);
+		assert_eq!(bits.to_string()[..4], *"0101");
+		assert_eq!(bits.get_field(0, 4).expect("Failed to get field"), 5);
+		assert_eq!(bits.get_field(4, 70).expect("Failed to get field"), 1);
+		assert_eq!(bits.get_field(1, 3).expect("Failed to get field"), 5);
+
+		assert!(bits.add_field(16, 4).is_err());
+		assert_eq!(bits.len(), 74);
+		assert!(bits.get_field(70, 5).is_err());
+		assert!(bits.get_field(usize::MAX, 2).is_err());
+		assert!(super::Bits::from_bool_slice(&[true; 70]).get_field(0, 70).is_err());
+
+		b.iter(|| {
+			let mut bits = super::Bits::new();
+			(0..1000).try_for_each(|value| bits.add_field(value, 32))?;
+			(0..1000).map(|index| bits.get_field(index * 32, 32)).sum::<anyhow::Result<usize>>()
+		});
+	}
+
+	// Join two collections with a length prefix
+	#[bench]
+	fn bits_concat_with_length_prefix(b: &mut Bencher) {
+		let header: super::Bits = "10110".parse().expect("Failed to parse bits");
+		let payload: super::Bits = "0011101".parse().expect("Failed to parse bits");
+
+		let joined = header.concat_with_length_prefix(&payload, 4).expect("Failed to join bits");
+		assert_eq!(joined.to_string(), "0101101100011101");
+		assert_eq!(joined.split_length_prefixed(4).expect("Failed to split bits"), (header.clone(), payload.clone()));
+
+		assert!(header.concat_with_length_prefix(&payload, 2).is_err());
+		assert!(joined.split_length_prefixed(20).is_err());
+		assert!("1111000".parse::<super::Bits>().unwrap().split_length_prefixed(4).is_err());
+
+		b.iter(|| black_box(&header).concat_with_length_prefix(&payload, 32));
+	}
+
+	// Pack bits least significant bit first
+	#[bench]
+	fn bits_to_bytes_ordered(b: &mut Bencher) {
+		let bits: super::Bits = "1100000101".parse().expect("Failed to parse bits");
+		assert_eq!(bits.to_bytes_ordered(BitOrder::MsbFirst), vec![0b1100_0001, 0b0100_0000]);
+		assert_eq!(bits.to_bytes_ordered(BitOrder::LsbFirst), vec![0b1000_0011, 0b0000_0010]);
+		assert_eq!(bits.to_bytes_ordered(BitOrder::MsbFirst), bits.to_bytes());
+
+		let mut long = super::Bits::new();
+		for index in 0..10_001 {
+			long.add(index % 3 == 0);
+		}
+		for order in [BitOrder::MsbFirst, BitOrder::LsbFirst] {
+			let bytes = long.to_bytes_ordered(order);
+			assert_eq!(super::Bits::from_bytes_ordered(&bytes, long.len(), order).expect("Failed to unpack bits"), long);
+			assert!(super::Bits::from_bytes_ordered(&bytes, bytes.len() * 8 + 1, order).is_err());
+		}
+
+		b.iter(|| black_box(&long).to_bytes_ordered(BitOrder::LsbFirst));
+	}
+
+	// Copy bits from a slice of booleans
+	#[bench]
+	fn bits_from_bool_slice(b: &mut Bencher) {
+		let slice: Vec<bool> = (0..10_000).map(|index| index % 3 == 0).collect();
+
+		let bits = super::Bits::from_bool_slice(&slice);
+		assert_eq!(bits.as_slice(), slice.as_slice());
+		assert_eq!(super::Bits::from_bool_slice(&[]), super::Bits::new());
+
+		b.iter(|| super::Bits::from_bool_slice(black_box(&slice)));
+	}
+
+	// Reverse bits in place
+	#[bench]
+	fn bits_reverse(b: &mut Bencher) {
+		let mut bits: super::Bits = "110100".parse().expect("Failed to parse bits");
+		bits.reverse();
+		assert_eq!(bits.to_string(), "001011");
+
+		let mut empty = super::Bits::new();
+		empty.reverse();
+		assert_eq!(empty.len(), 0);
+
+		b.iter(|| black_box(&mut bits).reverse());
+	}
+
+	// Insert a bit at the front
+	#[bench]
+	fn bits_insert(b: &mut Bencher) {
+		let mut bits: super::Bits = "0110".parse().expect("Failed to parse bits");
+		bits.insert(0, true).expect("Failed to insert bit");
+		bits.insert(5, true).expect("Failed to insert bit");
+		bits.insert(3, false).expect("Failed to insert bit");
+		assert_eq!(bits.to_string(), "1010101");
+		assert!(bits.insert(8, true).is_err());
+
+		let mut bits = super::Bits::from_bool_slice(&[false; 1_000]);
+		b.iter(|| {
+			bits.insert(black_box(0), true).expect("Failed to insert bit");
+			bits.collection.pop();
+		});
+	}
+
+	// Patch a header in front of a payload
+	#[bench]
+	fn bits_splice(b: &mut Bencher) {
+		let mut bits: super::Bits = "000011".parse().expect("Failed to parse bits");
+		let header: super::Bits = "101".parse().expect("Failed to parse bits");
+
+		bits.splice(0..4, &header).expect("Failed to splice bits");
+		assert_eq!(bits.to_string(), "10111");
+		bits.splice(5..5, &header).expect("Failed to splice bits");
+		assert_eq!(bits.to_string(), "10111101");
+		bits.splice(1..7, &super::Bits::new()).expect("Failed to splice bits");
+		assert_eq!(bits.to_string(), "11");
+		assert!(bits.splice(1..3, &header).is_err());
+		#[allow(clippy::reversed_empty_ranges)]
+		let backwards = 2..1;
+		assert!(bits.splice(backwards, &header).is_err());
+
+		let mut bits = super::Bits::from_bool_slice(&[false; 1_000]);
+		b.iter(|| bits.splice(black_box(0..3), &header));
+	}
+
+	// Write bits in groups of a byte
+	#[bench]
+	fn bits_to_string_grouped(b: &mut Bencher) {
+		let bits: super::Bits = "101100100110".parse().expect("Failed to parse bits");
+		assert_eq!(bits.to_string_grouped(8, ' '), "10110010 0110");
+		assert_eq!(bits.to_string_grouped(4, '_'), "1011_0010_0110");
+		assert_eq!(bits.to_string_grouped(12, ' '), bits.to_string());
+		assert_eq!(bits.to_string_grouped(0, ' '), bits.to_string());
+		assert_eq!(super::Bits::new().to_string_grouped(8, ' '), "");
+
+		let mut bits = super::Bits::new();
+		for index in 0..10_000 {
+			bits.add(index % 3 == 0);
+		}
+		b.iter(|| black_box(&bits).to_string_grouped(8, ' '));
+	}
+
+	// Walk bits a byte at a time
+	#[bench]
+	fn bits_chunks(b: &mut Bencher) {
+		let bits: super::Bits = "101100100110".parse().expect("Failed to parse bits");
+		let chunks: Vec<&[bool]> = bits.chunks(8).collect();
+		assert_eq!(chunks, [&bits.as_slice()[..8], &bits.as_slice()[8..]]);
+		assert_eq!(chunks[1], [false, true, true, false]);
+		assert_eq!(bits.chunks(4).count(), 3);
+		assert_eq!(bits.chunks(100).collect::<Vec<_>>(), [bits.as_slice()]);
+		assert_eq!(super::Bits::new().chunks(8).count(), 0);
+
+		let packed: Vec<u8> = bits.chunks(8)
+			.map(|chunk| chunk.iter().enumerate().fold(0, |byte, (index, bit)| byte | u8::from(*bit) << (7 - index)))
+			.collect();
+		assert_eq!(packed, bits.to_bytes());
+
+		let mut bits = super::Bits::new();
+		for index in 0..10_000 {
+			bits.add(index % 3 == 0);
+		}
+		b.iter(|| black_box(&bits).chunks(8).filter(|chunk| chunk[0]).count());
+	}
+
+	// Reserve space ahead of adding bits
+	#[bench]
+	fn bits_reserve(b: &mut Bencher) {
+		let mut bits: super::Bits = "1011".parse().expect("Failed to parse bits");
+		bits.reserve(100);
+		let capacity = bits.capacity();
+		assert!(capacity >= bits.len() + 100);
+
+		while bits.len() < capacity {
+			bits.add(true);
+		}
+		assert_eq!(bits.capacity(), capacity);
+
+		b.iter(|| {
+			let mut bits = super::Bits::new();
+			bits.reserve(black_box(10_000));
+			bits
+		});
+	}
+
+	// Append bits written as a string
+	#[bench]
+	fn bits_extend_from_str(b: &mut Bencher) {
+		let mut bits: super::Bits = "101".parse().expect("Failed to parse bits");
+		bits.extend_from_str("0011").expect("Failed to extend bits");
+		assert_eq!(bits.to_string(), "1010011");
+
+		let before = bits.clone();
+		assert!(bits.extend_from_str("01x10").is_err());
+		assert_eq!(bits, before);
+		assert_eq!(bits.len(), 7);
+
+		let string = "01".repeat(5_000);
+		b.iter(|| {
+			let mut bits = super::Bits::new();
+			bits.extend_from_str(black_box(&string))
+		});
+	}
+
+	// Count set bits
+	#[bench]
+	fn bits_count_ones(b: &mut Bencher) {
+		let empty = super::Bits::new();
+		assert_eq!(empty.count_ones(), 0);
+		assert_eq!(empty.count_zeros(), 0);
+
+		let mut bits = super::Bits::new();
+		for index in 0..10_000 {
+			bits.add(index % 3 == 0);
+		}
+		assert_eq!(bits.count_ones(), 3_334);
+		assert_eq!(bits.count_zeros(), 6_666);
+
+		b.iter(|| black_box(&bits).count_ones());
+	}
+
 	// Add bits
 	#[bench]
 	fn bits_add(b: &mut Bencher) {
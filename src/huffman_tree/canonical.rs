@@ -0,0 +1,49 @@
+use anyhow::{anyhow, Result};
+use super::bits::Bits;
+
+/// Assigns canonical codes to symbols from their code lengths
+///
+/// Symbols are sorted by code length and then by symbol. The first gets a code
+/// of all zeros, and each one after gets the previous code plus one, extended
+/// with zeros to its own length. Running out of codes of a given length means
+/// the lengths break the Kraft inequality, so no prefix code has them.
+pub fn canonical_codes(lengths: &[(char, usize)]) -> Result<Vec<(char, Bits)>> {
+	if lengths.iter().any(|(_, length)| *length == 0) {
+		return Err(anyhow!("Code lengths must be at least 1"));
+	}
+
+	let mut sorted = lengths.to_vec();
+	sorted.sort_unstable_by_key(|(character, length)| (*length, *character));
+
+	let mut codes = Vec::with_capacity(sorted.len());
+	let mut code = Vec::new();
+
+	for (index, (character, length)) in sorted.into_iter().enumerate() {
+		if index > 0 && !increment(&mut code) {
+			return Err(anyhow!("Code lengths do not satisfy the Kraft inequality"));
+		}
+
+		code.resize(length, false);
+
+		let mut bits = Bits::new();
+		code.iter().for_each(|bit| bits.add(*bit));
+		codes.push((character, bits));
+	}
+
+	Ok(codes)
+}
+
+/// Adds one to a binary number stored most significant bit first
+///
+/// Returns false if the number was all ones and overflowed.
+fn increment(code: &mut [bool]) -> bool {
+	for bit in code.iter_mut().rev() {
+		*bit = !*bit;
+
+		if *bit {
+			return true;
+		}
+	}
+
+	false
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use super::bits::Bits;
+use super::huffman_tree::HuffmanTree;
+use super::ngram::NgramTree;
+
+/// A coder that turns text into bits and back
+///
+/// Pipelines written against this trait can swap one coder for another. Any
+/// text `encode` accepts must `decode` back to the same text.
+pub trait Codec {
+	/// Encodes text into bits
+	fn encode(&self, input: &str) -> Result<Bits>;
+
+	/// Decodes bits made by `encode` back into text
+	fn decode(&self, bits: &Bits) -> Result<String>;
+}
+
+impl Codec for HuffmanTree {
+	fn encode(&self, input: &str) -> Result<Bits> {
+		HuffmanTree::encode(self, input)
+	}
+
+	fn decode(&self, bits: &Bits) -> Result<String> {
+		HuffmanTree::decode(self, bits)
+	}
+}
+
+impl Codec for NgramTree {
+	fn encode(&self, input: &str) -> Result<Bits> {
+		NgramTree::encode(self, input)
+	}
+
+	fn decode(&self, bits: &Bits) -> Result<String> {
+		NgramTree::decode(self, bits)
+	}
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+	#[allow(unused)]
+	use test::black_box;
+	#[allow(unused)]
+	use super::{Codec, HuffmanTree};
+
+	// Encode and decode with any codec
+	#[allow(unused)]
+	fn round_trip(codec: &dyn Codec, text: &str) -> anyhow::Result<String> {
+		codec.decode(&codec.encode(text)?)
+	}
+
+	// Round trip text through each codec behind the trait
+	#[bench]
+	fn codec_round_trip(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let ngrams = HuffmanTree::new_ngram(&text, 2)
+			.expect("Failed to build n-gram tree");
+
+		let codecs: [&dyn Codec; 2] = [&tree, &ngrams];
+		for codec in codecs {
+			assert_eq!(round_trip(codec, &text).expect("Failed to round trip"), text);
+		}
+		assert_eq!(Codec::encode(&tree, &text).expect("Failed to encode message"), tree.encode(&text).expect("Failed to encode message"));
+
+		b.bytes = text.len() as u64;
+		b.iter(|| round_trip(black_box(&tree), &text));
+	}
+}
@@ -0,0 +1,64 @@
+use anyhow::{anyhow, Result};
+use super::huffman_tree::HuffmanTree;
+
+/// An iterator that lazily decodes characters from a stream of bits
+///
+/// Bits that do not follow a path through the tree produce an error and
+/// decoding restarts from the root on the next bit. Bits that end partway
+/// through a code produce one final error. The tree's end of stream symbol, if
+/// it has one, ends the iterator and any bits after it are ignored. After an
+/// escape symbol, the next 21 bits are read as a raw codepoint.
+pub struct DecodeIter<'a, I> {
+	tree: &'a HuffmanTree,
+	bits: I,
+	finished: bool,
+}
+
+impl<'a, I: Iterator<Item = bool>> DecodeIter<'a, I> {
+	/// Constructs a new decoding iterator
+	///
+	/// * `tree` - The tree to decode with
+	/// * `bits` - The bits to decode, in order
+	pub fn new(tree: &'a HuffmanTree, bits: I) -> Self {
+		DecodeIter {tree, bits, finished: false}
+	}
+}
+
+impl<I: Iterator<Item = bool>> Iterator for DecodeIter<'_, I> {
+	type Item = Result<char>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.finished {
+			return None;
+		}
+
+		let tree = self.tree;
+		let mut node = tree.root();
+		let mut partial = false;
+
+		for bit in self.bits.by_ref() {
+			partial = true;
+
+			match tree.step(node, bit) {
+				Some(next) => node = next,
+				None => return Some(Err(anyhow!("Bits do not match any code in the tree")))
+			}
+
+			if let Some(value) = node.value {
+				if Some(value) == tree.eof() {
+					self.finished = true;
+					return None;
+				}
+
+				if Some(value) == tree.escape() {
+					return Some(HuffmanTree::read_escaped(&mut self.bits));
+				}
+
+				return Some(Ok(value));
+			}
+		}
+
+		self.finished = true;
+		partial.then(|| Err(anyhow!("Bits end partway through a code")))
+	}
+}
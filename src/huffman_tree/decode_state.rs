@@ -0,0 +1,93 @@
+use anyhow::{anyhow, Context, Result};
+use std::sync::Arc;
+use super::huffman_node::Node;
+use super::huffman_tree::{ESCAPE_CODEPOINT_BITS, HuffmanTree};
+
+/// A decoder that is fed one bit at a time
+///
+/// This suits bits arriving in fragments, like from a network stream, where
+/// there is no iterator to pull from. The position in the tree is kept between
+/// calls and goes back to the root after each decoded symbol.
+pub struct DecodeState<'a> {
+	tree: &'a HuffmanTree,
+	node: &'a Arc<Node>,
+	finished: bool,
+	/// The bits of an escaped codepoint read so far, and how many there are
+	escaped: Option<(u32, usize)>,
+}
+
+impl<'a> DecodeState<'a> {
+	/// Constructs a decoder starting at the root of a tree
+	pub fn new(tree: &'a HuffmanTree) -> Self {
+		DecodeState {tree, node: tree.root(), finished: false, escaped: None}
+	}
+
+	/// Follows one more bit down the tree
+	///
+	/// This gives None partway through a code and the symbol once a code is
+	/// complete. Bits that do not follow a path through the tree give an error
+	/// and decoding restarts from the root. Once the tree's end of stream
+	/// symbol is decoded, the decoder is finished and ignores any further bits.
+	/// After an escape symbol, the next 21 bits are collected as a raw
+	/// codepoint.
+	pub fn feed(&mut self, bit: bool) -> Result<Option<char>> {
+		if self.finished {
+			return Ok(None);
+		}
+
+		if let Some((codepoint, count)) = self.escaped {
+			let codepoint = codepoint << 1 | u32::from(bit);
+			if count + 1 < ESCAPE_CODEPOINT_BITS {
+				self.escaped = Some((codepoint, count + 1));
+				return Ok(None);
+			}
+
+			self.escaped = None;
+			return char::from_u32(codepoint)
+				.map(Some)
+				.with_context(|| format!("Escaped codepoint {codepoint:#X} is not a valid character"));
+		}
+
+		let Some(next) = self.tree.step(self.node, bit) else {
+			self.reset();
+			return Err(anyhow!("Bits do not match any code in the tree"));
+		};
+
+		match next.value {
+			Some(value) if Some(value) == self.tree.eof() => {
+				self.reset();
+				self.finished = true;
+				Ok(None)
+			}
+			Some(value) if Some(value) == self.tree.escape() => {
+				self.reset();
+				self.escaped = Some((0, 0));
+				Ok(None)
+			}
+			Some(value) => {
+				self.reset();
+				Ok(Some(value))
+			}
+			None => {
+				self.node = next;
+				Ok(None)
+			}
+		}
+	}
+
+	/// Checks whether the decoder is between codes rather than partway through one
+	pub fn is_at_boundary(&self) -> bool {
+		Arc::ptr_eq(self.node, self.tree.root()) && self.escaped.is_none()
+	}
+
+	/// Checks whether the end of stream symbol has been decoded
+	pub fn is_finished(&self) -> bool {
+		self.finished
+	}
+
+	/// Drops any partly decoded code and goes back to the root
+	pub fn reset(&mut self) {
+		self.node = self.tree.root();
+		self.escaped = None;
+	}
+}
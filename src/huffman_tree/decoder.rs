@@ -0,0 +1,99 @@
+use anyhow::Result;
+use super::bits::Bits;
+use super::huffman_tree::{ESCAPE_CODEPOINT_BITS, HuffmanTree};
+
+/// The most bits a decoder looks up at once
+///
+/// A 10 bit table has 1024 entries, which covers the common codes of most text
+/// while staying small enough to build quickly.
+const MAX_TABLE_BITS: usize = 10;
+
+#[derive(Clone)]
+/// A table-driven decoder for a Huffman tree
+///
+/// Instead of walking the tree one bit at a time, the next few bits are read
+/// as a number and looked up in a table of the symbol and code length they
+/// start with. Codes longer than the table, and bits that match no code, fall
+/// back to walking the tree.
+pub struct Decoder {
+	table_bits: usize,
+	table: Vec<Option<(char, u8)>>,
+}
+
+impl Decoder {
+	/// Constructs a decoder from a codebook
+	///
+	/// * `codes` - Every symbol and its code
+	pub fn new<'a>(codes: impl Iterator<Item = (char, &'a Bits)>) -> Self {
+		let codes: Vec<_> = codes.collect();
+		let table_bits = codes.iter()
+			.map(|(_, code)| code.len())
+			.max()
+			.unwrap_or(0)
+			.min(MAX_TABLE_BITS);
+
+		let mut table = vec![None; 1 << table_bits];
+
+		for (character, code) in codes {
+			if code.len() > table_bits {
+				continue;
+			}
+
+			let prefix = code.iter().fold(0, |value, bit| (value << 1) | usize::from(bit));
+			let spare_bits = table_bits - code.len();
+			let start = prefix << spare_bits;
+			let end = (prefix + 1) << spare_bits;
+
+			table[start..end].fill(Some((character, code.len() as u8)));
+		}
+
+		Decoder {table_bits, table}
+	}
+
+	/// Decodes a Bits object back into a string
+	///
+	/// This fails in the same cases as `HuffmanTree::decode`. Decoding stops at
+	/// the tree's end of stream symbol, if it has one, and the codepoint after
+	/// an escape symbol is read raw.
+	///
+	/// * `tree` - The tree this decoder was built from, for codes the table
+	///   cannot handle
+	/// * `bits` - The bits to decode
+	pub fn decode(&self, tree: &HuffmanTree, bits: &Bits) -> Result<String> {
+		let mut decoded = String::new();
+		let mut position = 0;
+
+		let eof = tree.eof();
+		let escape = tree.escape();
+
+		while position < bits.len() {
+			let remaining = bits.len() - position;
+
+			let (character, length) = match self.table[self.peek(bits, position)] {
+				Some((character, length)) if length as usize <= remaining => (character, length as usize),
+				_ => tree.walk_symbol(bits, position)?
+			};
+
+			if Some(character) == eof {
+				break;
+			}
+
+			position += length;
+			if Some(character) == escape {
+				decoded.push(HuffmanTree::read_escaped(&mut bits.iter().skip(position))?);
+				position += ESCAPE_CODEPOINT_BITS;
+			} else {
+				decoded.push(character);
+			}
+		}
+
+		Ok(decoded)
+	}
+
+	/// Reads the table index at a position, padding past the end with zeros
+	fn peek(&self, bits: &Bits, position: usize) -> usize {
+		(position..position + self.table_bits).fold(0, |value, index| {
+			(value << 1) | usize::from(bits.get(index).unwrap_or(false))
+		})
+	}
+}
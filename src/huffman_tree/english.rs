@@ -0,0 +1,41 @@
+use super::frequencies::Frequencies;
+
+/// Typical counts of lowercase letters per 100,000 letters of English text
+const LETTERS: [(char, usize); 26] = [
+	('a', 8167), ('b', 1492), ('c', 2782), ('d', 4253), ('e', 12702),
+	('f', 2228), ('g', 2015), ('h', 6094), ('i', 6966), ('j', 153),
+	('k', 772), ('l', 4025), ('m', 2406), ('n', 6749), ('o', 7507),
+	('p', 1929), ('q', 95), ('r', 5987), ('s', 6327), ('t', 9056),
+	('u', 2758), ('v', 978), ('w', 2360), ('x', 150), ('y', 1974),
+	('z', 74),
+];
+
+/// Typical counts of other common characters on the same scale as `LETTERS`
+const OTHERS: [(char, usize); 23] = [
+	(' ', 20000), ('\n', 400), (',', 1200), ('.', 1100), ('\'', 300),
+	('"', 300), ('-', 200), ('?', 100), ('!', 80), (':', 50),
+	(';', 40), ('(', 30), (')', 30), ('0', 150), ('1', 150),
+	('2', 100), ('3', 60), ('4', 50), ('5', 50), ('6', 40),
+	('7', 40), ('8', 40), ('9', 50),
+];
+
+/// The share of letters that are uppercase, as one in this many
+const UPPERCASE_RATIO: usize = 20;
+
+/// Builds a frequency table for typical English text
+///
+/// Every printable ASCII character, tab and newline has a count of at least 1,
+/// so any ASCII text can be encoded, though rare characters get long codes.
+pub fn english_frequencies() -> Frequencies {
+	let mut frequencies: Frequencies = (' '..='~').chain(['\t', '\n'])
+		.map(|character| (character, 1))
+		.collect();
+
+	for (letter, count) in LETTERS {
+		frequencies.insert(letter, count);
+		frequencies.insert(letter.to_ascii_uppercase(), (count / UPPERCASE_RATIO).max(1));
+	}
+
+	frequencies.extend(OTHERS);
+	frequencies
+}
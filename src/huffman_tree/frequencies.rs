@@ -0,0 +1,142 @@
+use anyhow::{anyhow, Result, Context};
+use fnv::FnvHasher;
+use std::{collections::HashMap, fs, hash::BuildHasherDefault, path::Path};
+use super::symbol::unescape_symbol;
+
+/// A map of characters and the number of times they appear
+pub type Frequencies = HashMap<char, usize, BuildHasherDefault<FnvHasher>>;
+
+/// A map of characters and their share of all the characters counted
+pub type Probabilities = HashMap<char, f64, BuildHasherDefault<FnvHasher>>;
+
+/// Converts counts into each symbol's relative frequency
+///
+/// Each probability is the symbol's count divided by the total, so they sum to
+/// 1. When the total is 0 every symbol gets a probability of 0 instead.
+pub fn frequencies_as_probabilities(frequencies: &Frequencies) -> Probabilities {
+	let total: usize = frequencies.values().sum();
+
+	frequencies.iter()
+		.map(|(character, count)| {
+			let probability = if total == 0 {0.0} else {*count as f64 / total as f64};
+			(*character, probability)
+		})
+		.collect()
+}
+
+/// Computes the Shannon entropy of a frequency table in bits per symbol
+///
+/// This is the lower bound on the average code length of any prefix code for
+/// symbols with these frequencies. Huffman codes are within 1 bit of it.
+pub fn entropy(frequencies: &Frequencies) -> f64 {
+	let total: usize = frequencies.values().sum();
+	if total == 0 {
+		return 0.0;
+	}
+
+	frequencies.values()
+		.filter(|count| **count > 0)
+		.map(|count| {
+			let probability = *count as f64 / total as f64;
+			-probability * probability.log2()
+		})
+		.sum()
+}
+
+/// Estimates the compressed size as a fraction of the original size
+///
+/// This uses the entropy of the frequencies instead of building a tree, so it
+/// is cheap enough to decide whether compressing an input is worth it at all.
+/// The original size is the UTF-8 encoding of the counted text, and the
+/// estimate leaves out the cost of storing the tree. Returns 0 for an empty
+/// table.
+pub fn estimate_savings(frequencies: &Frequencies) -> f64 {
+	let original_bits: usize = frequencies.iter()
+		.map(|(character, count)| character.len_utf8() * 8 * count)
+		.sum();
+
+	if original_bits == 0 {
+		return 0.0;
+	}
+
+	let symbols: usize = frequencies.values().sum();
+	entropy(frequencies) * symbols as f64 / original_bits as f64
+}
+
+/// Loads a frequency table from a file
+///
+/// Each line holds a symbol and its count separated by a tab. Symbols that are
+/// tabs, newlines, carriage returns or backslashes are written as `\t`, `\n`,
+/// `\r` and `\\`, and any symbol may be written as its codepoint, like
+/// `\u{1B}`. Blank lines are ignored.
+pub fn load_frequencies<P: AsRef<Path>>(path: P) -> Result<Frequencies> {
+	let contents = fs::read_to_string(path)
+		.context("Failed to read frequency file")?;
+
+	parse_frequencies(&contents)
+}
+
+/// Parses a frequency table from the contents of a frequency file
+fn parse_frequencies(contents: &str) -> Result<Frequencies> {
+	let mut frequencies = Frequencies::default();
+
+	for (number, line) in contents.lines().enumerate() {
+		if line.is_empty() {
+			continue;
+		}
+
+		let (symbol, count) = parse_line(line)
+			.with_context(|| format!("Invalid frequency on line {}", number + 1))?;
+
+		if frequencies.insert(symbol, count).is_some() {
+			return Err(anyhow!("Duplicate symbol on line {}", number + 1));
+		}
+	}
+
+	Ok(frequencies)
+}
+
+/// Parses a single `symbol<TAB>count` line
+fn parse_line(line: &str) -> Result<(char, usize)> {
+	let (symbol, count) = line.split_once('\t')
+		.context("Missing tab between symbol and count")?;
+
+	let symbol = unescape_symbol(symbol)?;
+	let count = count.parse()
+		.context("Count is not a number")?;
+
+	Ok((symbol, count))
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+	#[allow(unused)]
+	use test::black_box;
+	#[allow(unused)]
+	use super::{Frequencies, frequencies_as_probabilities};
+
+	// Convert the input file's counts into probabilities
+	#[bench]
+	fn frequencies_as_probabilities_text(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let frequencies = super::super::huffman_tree::get_letter_frequencies(&text);
+
+		let probabilities = frequencies_as_probabilities(&frequencies);
+		assert_eq!(probabilities.len(), frequencies.len());
+		assert!((probabilities.values().sum::<f64>() - 1.0).abs() < 1e-9);
+
+		let counts: Frequencies = [('a', 3), ('b', 1), ('c', 0)].into_iter().collect();
+		let probabilities = frequencies_as_probabilities(&counts);
+		assert_eq!(probabilities[&'a'], 0.75);
+		assert_eq!(probabilities[&'b'], 0.25);
+		assert_eq!(probabilities[&'c'], 0.0);
+
+		let zeros: Frequencies = [('a', 0)].into_iter().collect();
+		assert_eq!(frequencies_as_probabilities(&zeros)[&'a'], 0.0);
+		assert!(frequencies_as_probabilities(&Frequencies::default()).is_empty());
+
+		b.iter(|| frequencies_as_probabilities(black_box(&frequencies)));
+	}
+}
@@ -0,0 +1,81 @@
+use super::frequencies::Frequencies;
+use super::huffman_tree::get_letter_frequencies;
+
+#[derive(Clone, Debug, Default)]
+/// Accumulates symbol frequencies from text fed in over time
+///
+/// Text can be added piece by piece as it arrives, and counters filled on
+/// different threads or machines can be merged, so a tree is built once from
+/// the combined counts. See `HuffmanTree::combine_training`.
+pub struct FrequencyCounter {
+	frequencies: Frequencies,
+}
+
+impl FrequencyCounter {
+	/// Constructs a counter with no symbols counted
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Counts every character in a piece of text
+	pub fn add_text(&mut self, text: &str) -> &mut Self {
+		for (character, count) in get_letter_frequencies(text) {
+			*self.frequencies.entry(character).or_insert(0) += count;
+		}
+
+		self
+	}
+
+	/// Adds every count from another counter into this one
+	pub fn merge(&mut self, other: &Self) -> &mut Self {
+		for (character, count) in &other.frequencies {
+			*self.frequencies.entry(*character).or_insert(0) += count;
+		}
+
+		self
+	}
+
+	/// Gets the number of characters counted so far
+	pub fn total(&self) -> usize {
+		self.frequencies.values().sum()
+	}
+
+	/// Gets the counts so far
+	pub fn frequencies(&self) -> &Frequencies {
+		&self.frequencies
+	}
+
+	/// Takes the counts, ready for `HuffmanTree::from_frequencies`
+	pub fn into_frequencies(self) -> Frequencies {
+		self.frequencies
+	}
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+	#[allow(unused)]
+	use test::black_box;
+	#[allow(unused)]
+	use super::FrequencyCounter;
+
+	// Count text in shards and merge the counts
+	#[bench]
+	fn merge_shards(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let (first, second) = text.split_at(text.len() / 2);
+
+		let mut counter = FrequencyCounter::new();
+		counter.add_text(first);
+		let mut other = FrequencyCounter::new();
+		other.add_text(second);
+		counter.merge(&other);
+
+		assert_eq!(counter.frequencies(), &super::get_letter_frequencies(&text));
+		assert_eq!(counter.total(), text.chars().count());
+		assert_eq!(FrequencyCounter::new().total(), 0);
+
+		b.iter(|| FrequencyCounter::new().add_text(black_box(first)).merge(&other).total());
+	}
+}
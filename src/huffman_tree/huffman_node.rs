@@ -1,10 +1,11 @@
-use std::rc::Rc;
+use std::sync::Arc;
+use super::bits::Bits;
 
 #[derive(Clone, Debug)]
 /// A node in a Huffman tree
 pub struct Node {
-	pub left_child: Option<Rc<Node>>,
-	pub right_child: Option<Rc<Node>>,
+	pub left_child: Option<Arc<Node>>,
+	pub right_child: Option<Arc<Node>>,
 	pub value: Option<char>,
 }
 
@@ -13,9 +14,9 @@ impl Node {
 	///
 	/// * `left` - The left child node
 	/// * `right` - The right child node
-	pub fn new_parent(left: Rc<Node>, right: Rc<Node>) -> Self {
-		let left_child = Some(Rc::clone(&left));
-		let right_child = Some(Rc::clone(&right));
+	pub fn new_parent(left: Arc<Node>, right: Arc<Node>) -> Self {
+		let left_child = Some(Arc::clone(&left));
+		let right_child = Some(Arc::clone(&right));
 
 		Node {left_child, right_child, value: None}
 	}
@@ -30,4 +31,32 @@ impl Node {
 			value: Some(character)
 		}
 	}
+
+	/// Constructs the subtree holding a set of codes
+	///
+	/// Every code must share the same first `depth` bits, and the codes must be
+	/// prefix-free. A branch that no code goes down is left empty.
+	///
+	/// * `codes` - The symbols and their codes
+	/// * `depth` - The number of bits already followed to reach this subtree
+	pub fn from_codes(codes: &[(char, &Bits)], depth: usize) -> Arc<Node> {
+		if let [(character, code)] = codes {
+			if code.len() == depth {
+				return Arc::new(Node::new_leaf(*character));
+			}
+		}
+
+		let (left, right): (Vec<_>, Vec<_>) = codes.iter()
+			.partition(|(_, code)| code.get(depth) == Some(false));
+
+		let child = |codes: Vec<(char, &Bits)>| {
+			(!codes.is_empty()).then(|| Node::from_codes(&codes, depth + 1))
+		};
+
+		Arc::new(Node {
+			left_child: child(left),
+			right_child: child(right),
+			value: None
+		})
+	}
 }
@@ -1,230 +1,3350 @@
 use anyhow::{anyhow, Result, Context};
 use fnv::{FnvHasher, FnvHashMap};
-use std::{rc::Rc, collections::HashMap, hash::BuildHasherDefault};
+use std::{borrow::Cow, collections::{HashMap, HashSet}, fs, sync::{Arc, OnceLock}, hash::{BuildHasher, BuildHasherDefault}, io::{Read, Write}, path::Path};
+use crate::crc32::crc32;
+use crate::container::{SYMBOL_COUNT_BITS, SYMBOL_ENTRY_BITS};
+use super::bit_convention::BitConvention;
+use super::bit_reader::BitReader;
+use super::bit_writer::BitWriter;
 use super::bits::Bits;
+use super::canonical::canonical_codes;
+use super::decode_iter::DecodeIter;
+use super::decode_state::DecodeState;
+use super::decoder::Decoder;
+use super::english::english_frequencies;
+use super::frequencies::Frequencies;
+use super::frequency_counter::FrequencyCounter;
 use super::huffman_node::Node;
+use super::ids::{id_to_symbol, symbol_to_id};
+use super::lookup_table::{LookupStrategy, LookupTable};
+use super::ngram::NgramTree;
+use super::packed_code::PackedCode;
+use super::prefix::is_prefix_free;
+use super::symbol::{escape_symbol, unescape_symbol};
+use super::tie_break::TieBreak;
 
-type NodeTable = Vec<(usize, Rc<Node>)>;
+type NodeTable = Vec<(usize, Arc<Node>)>;
+type ByteTable = [Option<Bits>; 256];
 
+/// The number of bytes of text each parallel encoding task works on
+#[cfg(feature = "rayon")]
+const PARALLEL_CHUNK_BYTES: usize = 1 << 16;
+
+/// The symbol reserved to mark the end of a stream, a Unicode noncharacter
+const EOF_SYMBOL: char = '\u{FFFF}';
+
+/// The symbol reserved to mark an escaped codepoint, a Unicode noncharacter
+const ESCAPE_SYMBOL: char = '\u{FFFE}';
+
+/// The number of bits an escaped codepoint is written in, enough for U+10FFFF
+pub(super) const ESCAPE_CODEPOINT_BITS: usize = 21;
+
+/// The longest code a tree may have
+///
+/// Trees built from frequencies never come close, since a code of length `n`
+/// needs a total count of at least the `n`th Fibonacci number, which passes
+/// `u64::MAX` before length 93. This only limits codebooks passed in directly,
+/// whose nodes are built and dropped recursively, one level per bit.
+const MAX_CODE_LENGTH: usize = 2048;
+
+/// The number of bytes of text in each block that is sampled or skipped
+const SAMPLE_BLOCK_BYTES: usize = 1 << 12;
+
+#[derive(Clone, Copy, Default)]
+/// Options chosen when a tree is constructed, kept for decoding and retraining
+struct Settings {
+	convention: BitConvention,
+	tie_break: TieBreak,
+	lookup_strategy: LookupStrategy,
+	eof: bool,
+	rare_threshold: Option<usize>,
+	normalize: Option<fn(char) -> char>,
+}
+
+#[derive(Clone)]
+/// A Huffman tree and the tables used to encode and decode with it
+///
+/// A tree is `Send` and `Sync`. Its nodes are shared with `Arc` and every
+/// method that encodes or decodes takes `&self`, so one trained tree can be
+/// wrapped in an `Arc` and used by many threads at once. The decoding table is
+/// built lazily behind a `OnceLock`, so the first threads to decode may race
+/// to build it but all of them see the same table.
+///
+/// Cloning a tree is cheap for its nodes, which are immutable and shared with
+/// the original, and copies the tables and any decoder already built. The
+/// `Debug` output is a summary of the codebook rather than every node.
 pub struct HuffmanTree {
-	lookup_table: Vec<Option<Bits>>
+	root: Arc<Node>,
+	settings: Settings,
+	lookup_table: LookupTable<Bits>,
+	byte_table: ByteTable,
+	packed_table: Option<LookupTable<PackedCode>>,
+	decoder: OnceLock<Decoder>
 }
 
-impl HuffmanTree {
-	/// Construct a new Huffman tree from example text
-	pub fn new(example_text: &String) -> Result<Self> {
-		let frequencies = get_letter_frequencies(&example_text);
-		let mut table = Self::init_table(frequencies);
+impl HuffmanTree {
+	/// Construct a new Huffman tree from example text
+	pub fn new(example_text: &str) -> Result<Self> {
+		let frequencies = get_letter_frequencies(example_text);
+		Self::from_frequencies(frequencies)
+	}
+
+	/// Construct a new Huffman tree from text and encode that same text
+	///
+	/// This is the common case of compressing one specific input. The
+	/// frequencies counted to build the tree also give the exact length of the
+	/// encoding, so the output is allocated once at its final size.
+	pub fn new_and_encode(text: &str) -> Result<(Self, Bits)> {
+		let frequencies = get_letter_frequencies(text);
+		let tree = Self::from_frequencies(frequencies.clone())?;
+
+		let mut bits = Bits::new();
+		bits.reserve(tree.total_encoded_bits(&frequencies)?);
+		tree.encode_into(text, &mut bits)?;
+
+		Ok((tree, bits))
+	}
+
+	/// Construct a new Huffman tree from example text with a bit convention
+	///
+	/// The convention decides which bit each branch is written as. Encoding
+	/// and decoding with the tree both follow it.
+	pub fn new_with_convention(example_text: &str, convention: BitConvention) -> Result<Self> {
+		let frequencies = get_letter_frequencies(example_text);
+		Self::build(frequencies, Settings {convention, ..Default::default()})
+	}
+
+	/// Construct a new Huffman tree from example text with a lookup strategy
+	///
+	/// The strategy picks how codes are stored for encoding. `Auto`, used by
+	/// `new`, suits most text. `DenseVec` trades memory for speed on alphabets
+	/// with a few high codepoints, and `HashMap` keeps memory small for any
+	/// alphabet.
+	pub fn new_with_lookup_strategy(example_text: &str, lookup_strategy: LookupStrategy) -> Result<Self> {
+		let frequencies = get_letter_frequencies(example_text);
+		Self::build(frequencies, Settings {lookup_strategy, ..Default::default()})
+	}
+
+	/// Construct a new Huffman tree with an end of stream symbol
+	///
+	/// The noncharacter U+FFFF is added to the alphabet with a count of 1, and
+	/// every encode appends its code. Decoding stops cleanly at it, so padding
+	/// after the end, like the zeros filling out a final byte, is never
+	/// mistaken for symbols and the symbol count need not be stored. The cost
+	/// is one extra symbol in the codebook and one long code per message.
+	///
+	/// This will error if the example text contains U+FFFF, and encoding text
+	/// that contains it will fail.
+	pub fn new_with_eof(example_text: &str) -> Result<Self> {
+		let frequencies = get_letter_frequencies(example_text);
+		Self::build(frequencies, Settings {eof: true, ..Default::default()})
+	}
+
+	/// Construct a new Huffman tree that escapes symbols rarer than a threshold
+	///
+	/// Every symbol seen fewer than `min_frequency` times is merged into one
+	/// escape symbol, the noncharacter U+FFFE, whose count is their total. This
+	/// shrinks the codebook and the depth of the tree for alphabets with a long
+	/// tail of rare symbols. A rare symbol is encoded as the escape code then
+	/// its codepoint in 21 bits, so each one costs the escape code plus 21
+	/// bits. Characters missing from the example text are escaped the same
+	/// way, so any text without U+FFFE can be encoded.
+	///
+	/// This will error if the example text contains U+FFFE, and encoding text
+	/// that contains it will fail.
+	pub fn new_with_rare_threshold(example_text: &str, min_frequency: usize) -> Result<Self> {
+		let frequencies = get_letter_frequencies(example_text);
+		Self::build(frequencies, Settings {rare_threshold: Some(min_frequency), ..Default::default()})
+	}
+
+	/// Construct a new Huffman tree that folds characters together before
+	/// counting them
+	///
+	/// Every character of the example text is passed through `normalize`, so
+	/// with `|c| c.to_ascii_lowercase()` 'A' and 'a' are counted as one symbol
+	/// and share a code. The function is kept in the tree and applied to text
+	/// before it is encoded or retrained on, and decoding gives the normalized
+	/// form. This is lossy, since text that differs only in what was folded
+	/// decodes the same, but suits uses like search indexes where case does not
+	/// matter. Lookups of a single character, like `try_code`, take symbols as
+	/// they are stored and are not normalized.
+	pub fn new_normalized(example_text: &str, normalize: fn(char) -> char) -> Result<Self> {
+		let normalized: String = example_text.chars().map(normalize).collect();
+		let frequencies = get_letter_frequencies(&normalized);
+		Self::build(frequencies, Settings {normalize: Some(normalize), ..Default::default()})
+	}
+
+	/// Construct a new Huffman tree from example text with a tie-breaking rule
+	///
+	/// The rule orders symbols with equal frequencies. The default, used by
+	/// `new`, orders them by symbol. A seeded rule gives trees that are
+	/// reproducible but can be varied by changing the seed.
+	pub fn new_with_tiebreak(example_text: &str, tie_break: TieBreak) -> Result<Self> {
+		let frequencies = get_letter_frequencies(example_text);
+		Self::build(frequencies, Settings {tie_break, ..Default::default()})
+	}
+
+	/// Construct a new Huffman tree from example text that covers an alphabet
+	///
+	/// Every symbol in `alphabet` gets a count of at least 1, so the tree can
+	/// encode it even if it never appears in the text. Each unseen symbol adds a
+	/// leaf with the lowest possible weight, so it gets one of the longest codes
+	/// and pushes the other rare symbols down about a bit. Frequent symbols keep
+	/// their short codes, so the cost to typical text is small.
+	pub fn new_over_alphabet(example_text: &str, alphabet: &[char]) -> Result<Self> {
+		let mut frequencies = get_letter_frequencies(example_text);
+
+		for character in alphabet {
+			frequencies.entry(*character).or_insert(1);
+		}
+
+		Self::from_frequencies(frequencies)
+	}
+
+	/// Construct a new Huffman tree from example binary data
+	///
+	/// Each byte value is a symbol, stored as the character with the same
+	/// codepoint (U+0000 to U+00FF). Use `encode_bytes` and
+	/// `decode_bytes_to_vec` with the resulting tree.
+	pub fn new_from_bytes(example_data: &[u8]) -> Result<Self> {
+		let frequencies = get_byte_frequencies(example_data);
+		Self::from_frequencies(frequencies)
+	}
+
+	/// Construct a new Huffman tree from frequencies sampled from example text
+	///
+	/// Only about `sample_rate` of the text, in evenly spread blocks of a few
+	/// kilobytes, is counted. The rest is only scanned for symbols that have not
+	/// been seen, which is much cheaper, and those get a count of 1. The tree
+	/// can therefore encode all of the text, but its codes may be slightly
+	/// longer than a tree trained on exact counts. This will error unless the
+	/// rate is above 0 and at most 1.
+	pub fn new_sampled(example_text: &str, sample_rate: f64) -> Result<Self> {
+		if !(sample_rate > 0.0 && sample_rate <= 1.0) {
+			return Err(anyhow!("Sample rate must be above 0 and at most 1, not {sample_rate}"));
+		}
+
+		let frequencies = get_sampled_frequencies(example_text, sample_rate);
+		Self::from_frequencies(frequencies)
+	}
+
+	/// Construct a new Huffman tree from example integer IDs
+	///
+	/// Data already split into tokens, like word IDs from a vocabulary, can be
+	/// compressed without turning it back into text. Each ID is stored as a
+	/// symbol, with IDs from U+D800 up shifted past the surrogates. Use
+	/// `encode_ids` and `decode_ids` with the resulting tree. This will error if
+	/// an ID is above U+10F7FF.
+	pub fn new_from_ids(example_ids: &[u32]) -> Result<Self> {
+		let frequencies = get_id_frequencies(example_ids)?;
+		Self::from_frequencies(frequencies)
+	}
+
+	/// Construct a tree over groups of `n` characters from example text
+	///
+	/// See `NgramTree`. Grams can compress better than single characters, at
+	/// the cost of a much larger codebook.
+	pub fn new_ngram(example_text: &str, n: usize) -> Result<NgramTree> {
+		NgramTree::new(example_text, n)
+	}
+
+	/// Construct a new Huffman tree from several pieces of example text
+	///
+	/// This counts each text separately and sums the counts, which avoids
+	/// concatenating them into one large string first.
+	pub fn new_from_texts(texts: &[&str]) -> Result<Self> {
+		let mut counter = FrequencyCounter::new();
+
+		for text in texts {
+			counter.add_text(text);
+		}
+
+		Self::from_frequencies(counter.into_frequencies())
+	}
+
+	/// Construct a new Huffman tree from the sum of two frequency counters
+	///
+	/// Counting can be split into shards on different threads or machines,
+	/// with the counters merged and a single tree built at the end. This will
+	/// error when both counters are empty.
+	pub fn combine_training(a: &FrequencyCounter, b: &FrequencyCounter) -> Result<Self> {
+		let mut combined = a.clone();
+		combined.merge(b);
+
+		Self::from_frequencies(combined.into_frequencies())
+	}
+
+	/// Construct a new Huffman tree from a map of character frequencies
+	///
+	/// This will error when the map is empty.
+	pub fn from_frequencies(frequencies: Frequencies) -> Result<Self> {
+		Self::build(frequencies, Settings::default())
+	}
+
+	/// Construct a new Huffman tree from a slice of symbols and their counts
+	///
+	/// This suits literal tables and counts already held in a Vec. The order of
+	/// the pairs does not matter, and the tree is the same one
+	/// `from_frequencies` builds from the same counts. This will error when the
+	/// slice is empty or a symbol appears more than once.
+	pub fn from_pairs(pairs: &[(char, usize)]) -> Result<Self> {
+		let mut frequencies = Frequencies::default();
+		frequencies.reserve(pairs.len());
+
+		for (character, count) in pairs {
+			if frequencies.insert(*character, *count).is_some() {
+				return Err(anyhow!("Symbol {character:?} appears more than once"));
+			}
+		}
+
+		Self::from_frequencies(frequencies)
+	}
+
+	/// Construct a Huffman tree from a built-in table of English frequencies
+	///
+	/// Short English strings can then be encoded without training text, and
+	/// the tree never has to be sent since any copy of this crate builds the
+	/// same one. Every printable ASCII character, tab and newline can be
+	/// encoded. Encoding any other character, like an accented letter or an
+	/// emoji, fails just like a character missing from training text.
+	pub fn english() -> Self {
+		Self::from_frequencies(english_frequencies())
+			.expect("Built-in English frequency table is empty")
+	}
+
+	/// Builds a Huffman tree from character frequencies and settings
+	fn build(mut frequencies: Frequencies, settings: Settings) -> Result<Self> {
+		Self::add_reserved_symbols(&mut frequencies, settings)?;
+		let root = Self::build_root(frequencies, settings.tie_break)?;
+		Ok(Self::from_root(root, settings))
+	}
+
+	/// Adds the escape and end of stream symbols to frequencies if the settings
+	/// use them
+	///
+	/// With a rare threshold, symbols below it are removed and their counts go
+	/// to the escape symbol, which always gets a count of at least 1. This will
+	/// error if a reserved symbol is already there.
+	fn add_reserved_symbols(frequencies: &mut Frequencies, settings: Settings) -> Result<()> {
+		if let Some(min_frequency) = settings.rare_threshold {
+			if frequencies.contains_key(&ESCAPE_SYMBOL) {
+				return Err(anyhow!("Text contains U+FFFE, which is reserved for escapes"));
+			}
+
+			let mut rare_count = 0;
+			frequencies.retain(|_, count| {
+				let rare = *count < min_frequency;
+				if rare {
+					rare_count += *count;
+				}
+				!rare
+			});
+
+			frequencies.insert(ESCAPE_SYMBOL, rare_count.max(1));
+		}
+
+		if settings.eof && frequencies.insert(EOF_SYMBOL, 1).is_some() {
+			return Err(anyhow!("Text contains U+FFFF, which is reserved for the end of stream"));
+		}
+
+		Ok(())
+	}
+
+	/// Builds the nodes of a Huffman tree from character frequencies
+	///
+	/// This will error when the map is empty.
+	fn build_root(frequencies: Frequencies, tie_break: TieBreak) -> Result<Arc<Node>> {
+		if frequencies.is_empty() {
+			return Err(anyhow!("Cannot build a Huffman tree without any symbols"));
+		}
+
+		let mut table = Self::init_table(frequencies, tie_break);
+
+		while table.len() > 1 {
+			Self::build_tree(&mut table)?;
+		}
+
+		Ok(Arc::clone(&table[0].1))
+	}
+
+	/// Rebuilds this tree in place from new example text
+	///
+	/// Every setting chosen at construction is kept: the bit convention,
+	/// tie-breaking rule, lookup strategy, end of stream symbol, rare symbol
+	/// threshold and normalization. The lookup table's allocation is cleared
+	/// and refilled when the new alphabet uses the same representation, which
+	/// saves allocator churn for applications that retrain periodically. Every
+	/// entry from the old training is replaced. On error the tree is left as it
+	/// was.
+	pub fn retrain(&mut self, example_text: &str) -> Result<()> {
+		let mut frequencies = get_letter_frequencies(&self.normalize(example_text));
+		Self::add_reserved_symbols(&mut frequencies, self.settings)?;
+		let root = Self::build_root(frequencies, self.settings.tie_break)?;
+
+		self.lookup_table.refill(Self::code_map(&root, self.settings.convention), self.settings.lookup_strategy);
+		for (index, slot) in self.byte_table.iter_mut().enumerate() {
+			*slot = self.lookup_table.get(char::from(index as u8)).cloned();
+		}
+		self.packed_table = Self::packed_table(&self.lookup_table, self.settings.lookup_strategy);
+
+		self.root = root;
+		self.decoder = OnceLock::new();
+		Ok(())
+	}
+
+	/// Construct a Huffman tree from an explicit codebook
+	///
+	/// The codes are used as given, with `0` meaning the left branch. This will
+	/// error when the codebook is empty, has an empty code or one over 2048
+	/// bits, lists a symbol more than once, or is not prefix-free.
+	///
+	/// Codebooks read from untrusted input are safe to pass in. The length
+	/// limit bounds the recursion that builds the nodes, and every decoder
+	/// walks the tree one level per bit, so no symbol takes more than 2048
+	/// steps to decode. Nodes are immutable and each one is built from its
+	/// finished children, so the tree cannot have a cycle to loop on.
+	pub fn from_codes(codes: &[(char, Bits)]) -> Result<Self> {
+		if codes.is_empty() {
+			return Err(anyhow!("Cannot build a Huffman tree without any symbols"));
+		}
+
+		if codes.iter().any(|(_, code)| code.len() == 0) {
+			return Err(anyhow!("Codes must be at least one bit long"));
+		}
+
+		if codes.iter().any(|(_, code)| code.len() > MAX_CODE_LENGTH) {
+			return Err(anyhow!("Codes must be at most {MAX_CODE_LENGTH} bits long"));
+		}
+
+		let mut symbols: Vec<char> = codes.iter().map(|(character, _)| *character).collect();
+		symbols.sort_unstable();
+		if symbols.windows(2).any(|pair| pair[0] == pair[1]) {
+			return Err(anyhow!("Codebook lists a symbol more than once"));
+		}
+
+		let bits: Vec<Bits> = codes.iter().map(|(_, code)| code.clone()).collect();
+		if !is_prefix_free(&bits) {
+			return Err(anyhow!("Codebook is not prefix-free"));
+		}
+
+		let codes: Vec<(char, &Bits)> = codes.iter()
+			.map(|(character, code)| (*character, code))
+			.collect();
+		let root = Node::from_codes(&codes, 0);
+
+		Ok(Self::from_root(root, Settings::default()))
+	}
+
+	/// Construct a Huffman tree with canonical codes from code lengths
+	///
+	/// This is the decoding side of `code_lengths`. It will error when the
+	/// lengths break the Kraft inequality, meaning no prefix code has them,
+	/// and in the same cases as `from_codes`. Lengths over the limit are
+	/// refused before any codes are made.
+	pub fn from_code_lengths(lengths: &[(char, usize)]) -> Result<Self> {
+		if lengths.iter().any(|(_, length)| *length > MAX_CODE_LENGTH) {
+			return Err(anyhow!("Codes must be at most {MAX_CODE_LENGTH} bits long"));
+		}
+
+		let codes = canonical_codes(lengths)?;
+		Self::from_codes(&codes)
+	}
+
+	/// Construct the fixed literal/length code from DEFLATE (RFC 1951)
+	///
+	/// Symbols 0 to 287 are the characters U+0000 to U+011F. Bytes are the
+	/// first 256, U+0100 is the end of block marker and the rest stand for
+	/// match lengths. The code lengths are:
+	///
+	/// * 0 to 143 - 8 bits
+	/// * 144 to 255 - 9 bits
+	/// * 256 to 279 - 7 bits
+	/// * 280 to 287 - 8 bits
+	///
+	/// The codes are canonical, so they match DEFLATE's bit for bit, like
+	/// `00110000` for byte 0 and `0000000` for the end of block. Any byte data
+	/// can be encoded with `encode_bytes` without sending a tree.
+	pub fn fixed_deflate() -> Self {
+		let lengths: Vec<(char, usize)> = (0..=287u32)
+			.map(|symbol| {
+				let length = match symbol {
+					0..=143 => 8,
+					144..=255 => 9,
+					256..=279 => 7,
+					_ => 8
+				};
+				(char::from_u32(symbol).unwrap(), length)
+			})
+			.collect();
+
+		Self::from_code_lengths(&lengths)
+			.expect("Fixed DEFLATE code lengths are invalid")
+	}
+
+	/// Builds the lookup tables for a finished tree
+	fn from_root(root: Arc<Node>, settings: Settings) -> Self {
+		let lookup_table = Self::convert_table(Self::code_map(&root, settings.convention), settings.lookup_strategy);
+		let byte_table = Self::byte_table(&lookup_table);
+		let packed_table = Self::packed_table(&lookup_table, settings.lookup_strategy);
+		HuffmanTree{root, settings, lookup_table, byte_table, packed_table, decoder: OnceLock::new()}
+	}
+
+	/// Collects the code of every symbol in a finished tree
+	fn code_map(root: &Arc<Node>, convention: BitConvention) -> HashMap<char, Bits, BuildHasherDefault<FnvHasher>> {
+		let left_bit = convention.left_bit();
+		let mut table = FnvHashMap::default();
+		Self::traverse(root, &mut table, &Self::root_code(root, left_bit), left_bit);
+
+		table
+	}
+
+	/// Gets the code prefix for the root of a tree
+	///
+	/// A tree with only one symbol has no edges, so that symbol is given the
+	/// left branch's bit instead of an empty code that could never be decoded.
+	fn root_code(root: &Node, left_bit: bool) -> Bits {
+		let mut code = Bits::new();
+		if root.value.is_some() {
+			code.add(left_bit);
+		}
+
+		code
+	}
+
+	/// Runs one step of building the tree
+	///
+	/// This will error when there are less than two nodes to combine.
+	fn build_tree(table: &mut NodeTable) -> Result<()> {
+		if table.len() < 2 {
+			return Err(anyhow!("Attempt to build tree without two nodes to combine"));
+		}
+
+		let (n1, left) = table.remove(0);
+		let (n2, right) = table.remove(0);
+
+		let num = n1 + n2;
+		let node = Arc::new(Node::new_parent(left, right));
+
+		Self::insert_table_entry(table, num, node);
+		Ok(())
+	}
+
+	/// Converts the hashmap table to a lookup table
+	///
+	/// Since there are usually only a few hundred options, the `Auto` strategy
+	/// sacrifices a bit of space in a Vec to avoid running the hash function.
+	/// Text with a very high codepoint is remapped to dense indices instead, so
+	/// one emoji does not cost a Vec with a hundred thousand empty slots.
+	fn convert_table(table: HashMap<char, Bits, BuildHasherDefault<FnvHasher>>, strategy: LookupStrategy) -> LookupTable<Bits> {
+		LookupTable::new_with_strategy(table, strategy)
+	}
+
+	/// Gets the depth of the tree, the length of its longest code
+	///
+	/// This is the worst-case number of bits spent on a single character. A
+	/// tree with only one symbol has a depth of 1, matching its 1 bit code.
+	pub fn depth(&self) -> usize {
+		self.lookup_table.values()
+			.map(Bits::len)
+			.max()
+			.unwrap_or(0)
+	}
+
+	/// Checks that no code is longer than a limit
+	///
+	/// Very skewed frequencies, like each symbol being about as common as all
+	/// rarer symbols together, give a tree as deep as the alphabet is large.
+	/// Formats that store code lengths in a fixed width, or decoders that read
+	/// a whole code at once, need a bound. This will error when the tree is
+	/// deeper than `max_length`, suggesting length-limited coding instead.
+	pub fn check_depth(&self, max_length: usize) -> Result<()> {
+		let depth = self.depth();
+		if depth > max_length {
+			return Err(anyhow!("Longest code is {depth} bits, over the limit of {max_length}; the frequencies are too skewed, so consider length-limited coding"));
+		}
+
+		Ok(())
+	}
+
+	/// Checks that text survives encoding and decoding with this tree
+	///
+	/// Text is decoded both with the cached decoder and by walking the tree,
+	/// so the lookup tables and the nodes are checked against each other. This
+	/// is worth running on trees built from codes or code lengths before
+	/// trusting them. It errors if the text cannot be encoded or decoded, or
+	/// decodes to something else.
+	pub fn self_check(&self, text: &str) -> Result<()> {
+		let bits = self.encode(text)
+			.context("Self check failed to encode text")?;
+
+		let decoded = self.decode(&bits)
+			.context("Self check failed to decode text")?;
+		let walked = self.decode_iter(&bits).collect::<Result<String>>()
+			.context("Self check failed to decode text by walking the tree")?;
+
+		if decoded != text || walked != text {
+			return Err(anyhow!("Self check decoded text that differs from the original"));
+		}
+
+		Ok(())
+	}
+
+	/// Gets the number of distinct symbols the tree can encode
+	///
+	/// This is the same as `alphabet_size`.
+	pub fn symbol_count(&self) -> usize {
+		self.alphabet_size()
+	}
+
+	/// Gets the number of distinct symbols in the tree's alphabet
+	///
+	/// This counts only the filled slots of the lookup table, not the empty
+	/// slots for characters below the largest symbol. An alphabet size of 1 is
+	/// the degenerate case where every character costs a single bit.
+	pub fn alphabet_size(&self) -> usize {
+		self.lookup_table.values()
+			.count()
+	}
+
+	/// Gets the fraction of lookup table slots that hold a code
+	///
+	/// Slots are counted as if the table were indexed by codepoint, so this is
+	/// the alphabet size over one more than the largest symbol. A density near
+	/// zero means a few high codepoints would spread a small alphabet across a
+	/// mostly empty table, which is when the table switches to remapped indices.
+	pub fn lookup_table_density(&self) -> f64 {
+		self.lookup_table.density()
+	}
+
+	/// Copies the first 256 characters of the lookup table into a fixed size array
+	///
+	/// Pure ASCII text, and byte data from trees trained on bytes, can then be
+	/// encoded byte by byte from a table small enough to stay in cache.
+	fn byte_table(lookup_table: &LookupTable<Bits>) -> ByteTable {
+		std::array::from_fn(|index| {
+			lookup_table.get(char::from(index as u8)).cloned()
+		})
+	}
+
+	/// Packs every code of the lookup table into an integer and a length
+	///
+	/// This gives None when any code is longer than 32 bits, which only very
+	/// skewed frequencies can cause. Encoders then fall back to the Bits codes.
+	fn packed_table(lookup_table: &LookupTable<Bits>, strategy: LookupStrategy) -> Option<LookupTable<PackedCode>> {
+		let packed_codes = lookup_table.iter()
+			.map(|(character, code)| Some((character, PackedCode::from_bits(code)?)))
+			.collect::<Option<FnvHashMap<char, PackedCode>>>()?;
+
+		Some(LookupTable::new_with_strategy(packed_codes, strategy))
+	}
+
+	/// Computes the Kraft sum of the codebook, the sum of 2^-length over all codes
+	///
+	/// A complete prefix code, like any Huffman code with two or more symbols,
+	/// sums to exactly 1. Less than 1 means some bit patterns decode to nothing,
+	/// and more than 1 means the codes cannot be prefix-free.
+	pub fn kraft_sum(&self) -> f64 {
+		self.lookup_table.values()
+			.map(|code| 0.5f64.powi(code.len() as i32))
+			.sum()
+	}
+
+	/// Checks that no two symbols share a code
+	///
+	/// Every valid Huffman tree passes, since each leaf has its own path, so a
+	/// failure points to a bug in construction or in codes passed to
+	/// `from_codes`. This is a cheap check for tests to run on any tree.
+	pub fn has_unique_codes(&self) -> bool {
+		let codes: HashSet<&Bits> = self.lookup_table.values().collect();
+		codes.len() == self.alphabet_size()
+	}
+
+	/// Gets the length of every symbol's code, sorted by symbol
+	///
+	/// With canonical codes, where codes are handed out in order of length and
+	/// then symbol, these lengths alone fully determine every code. This is the
+	/// compact form formats like DEFLATE and JPEG use to store a codebook.
+	pub fn code_lengths(&self) -> Vec<(char, usize)> {
+		let mut lengths: Vec<_> = self.lookup_table.iter()
+			.map(|(character, code)| (character, code.len()))
+			.collect();
+
+		lengths.sort_unstable();
+		lengths
+	}
+
+	/// Lists every symbol with its code, sorted by code length and then symbol
+	///
+	/// This is the order canonical codes are handed out in, and the usual order
+	/// for serializing and comparing codebooks. The codes are this tree's own,
+	/// which only match `canonical_codes` for a tree built with
+	/// `from_code_lengths`.
+	pub fn canonical_order(&self) -> Vec<(char, Bits)> {
+		let mut codes: Vec<_> = self.lookup_table.iter()
+			.map(|(character, code)| (character, code.clone()))
+			.collect();
+
+		codes.sort_unstable_by_key(|(character, code)| (code.len(), *character));
+		codes
+	}
+
+	/// Computes the number of bits symbols with these frequencies encode to
+	///
+	/// This is the sum of each symbol's frequency times its code length. For
+	/// the frequencies of the training text it is also the sum of every merged
+	/// node's weight, the cost Huffman's algorithm minimizes, and it always
+	/// equals the length of `encode` on text with these frequencies. Escaped
+	/// symbols count as the escape code plus 21 bits. This will fail if a
+	/// symbol with a nonzero frequency has no code and cannot be escaped.
+	///
+	/// Only the counts are needed, not the text, so this can size a buffer
+	/// when the distribution of a message is known before the message itself.
+	/// Any map of counts works, whatever its hasher.
+	pub fn total_encoded_bits<S: BuildHasher>(&self, frequencies: &HashMap<char, usize, S>) -> Result<usize> {
+		let eof_bits = self.eof().map_or(0, |eof| self.lookup_table.get(eof).map_or(0, Bits::len));
+
+		frequencies.iter()
+			.filter(|(_, count)| **count > 0)
+			.map(|(character, count)| Ok(self.code_len(*character)? * count))
+			.sum::<Result<usize>>()
+			.map(|bits| bits + eof_bits)
+	}
+
+	/// Gets the number of bits the tree takes when stored in a container
+	///
+	/// This is the codebook `compress` writes: a 32 bit count of symbols, then
+	/// each symbol's 32 bit codepoint and 8 bit code length. The code lengths
+	/// are enough to rebuild canonical codes, so this is the size of sending
+	/// the tree alongside a message.
+	pub fn serialized_bits(&self) -> usize {
+		SYMBOL_COUNT_BITS + SYMBOL_ENTRY_BITS * self.alphabet_size()
+	}
+
+	/// Computes how many bits encoding text saves once the tree is sent too
+	///
+	/// This is the baseline of 8 bits per character, minus the bits `encode`
+	/// gives and the `serialized_bits` of the tree. For short text the tree can
+	/// cost more than the encoding saves, and a negative result means the text
+	/// is smaller left as it is. This will fail in the same cases as `encode`.
+	pub fn net_savings(&self, text: &str) -> Result<i64> {
+		let baseline_bits = text.chars().count() * 8;
+		let encoded_bits = self.total_encoded_bits(&get_letter_frequencies(text))?;
+
+		Ok(baseline_bits as i64 - (encoded_bits + self.serialized_bits()) as i64)
+	}
+
+	/// Computes the exact number of bits text with these counts encodes to
+	///
+	/// This is `total_encoded_bits` for callers planning buffer sizes who know
+	/// every symbol can be encoded.
+	///
+	/// # Panics
+	///
+	/// Panics if a symbol with a nonzero count has no code and cannot be
+	/// escaped. Use `total_encoded_bits` to get an error instead.
+	pub fn estimated_bits_for(&self, freqs: &HashMap<char, usize>) -> usize {
+		self.total_encoded_bits(freqs)
+			.expect("Frequencies contain a symbol the tree cannot encode")
+	}
+
+	/// Computes how many bits each symbol contributes to the encoded output
+	///
+	/// Each symbol's cost is its frequency times its code length, with escaped
+	/// symbols counting the escape code plus 21 bits. The costs are sorted from
+	/// largest to smallest, then by symbol, so the first few show which symbols
+	/// dominate the output and are worth coding as n-grams or merging. Symbols
+	/// with a frequency of 0 are left out. This will fail if a symbol with a
+	/// nonzero frequency has no code and cannot be escaped.
+	pub fn symbol_cost(&self, frequencies: &Frequencies) -> Result<Vec<(char, usize)>> {
+		let mut costs = frequencies.iter()
+			.filter(|(_, count)| **count > 0)
+			.map(|(character, count)| Ok((*character, self.code_len(*character)? * count)))
+			.collect::<Result<Vec<(char, usize)>>>()?;
+
+		costs.sort_unstable_by_key(|(character, cost)| (std::cmp::Reverse(*cost), *character));
+		Ok(costs)
+	}
+
+	/// Exports every symbol with its code packed into an integer and its length
+	///
+	/// Symbols are sorted by codepoint. Each code is the low `length` bits of
+	/// its integer, first bit most significant, which is everything needed to
+	/// store the codebook in a custom format and rebuild it with `from_codes`.
+	/// This will error if any code is longer than 32 bits.
+	pub fn export_codebook(&self) -> Result<Vec<(char, u32, u8)>> {
+		let packed_table = self.packed_table.as_ref()
+			.context("Codes longer than 32 bits cannot be exported")?;
+
+		Ok(packed_table.iter()
+			.map(|(character, code)| (character, code.bits, code.len))
+			.collect())
+	}
+
+	/// Writes the codebook as a C header for decoders without the Rust runtime
+	///
+	/// The header defines `<name>_codes`, an array of `{code, length}` pairs
+	/// from `export_codebook`, and `<name>_symbols`, the codepoint each pair
+	/// encodes, both `<NAME>_SYMBOLS` long and sorted by codepoint. Each code
+	/// is the low `length` bits of `code`, first bit most significant, so a
+	/// firmware decoder can match codes without building a tree. This will
+	/// error if `name` is not a C identifier, and in the same cases as
+	/// `export_codebook`.
+	pub fn to_c_header(&self, name: &str) -> Result<String> {
+		let is_identifier = name.chars().next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+			&& name.chars().all(|character| character.is_ascii_alphanumeric() || character == '_');
+		if !is_identifier {
+			return Err(anyhow!("{name:?} is not a valid C identifier"));
+		}
+
+		let codebook = self.export_codebook()?;
+		let upper = name.to_ascii_uppercase();
+
+		let mut header = format!("#ifndef {upper}_H\n#define {upper}_H\n\n#include <stdint.h>\n\n");
+		header.push_str(&format!("#define {upper}_SYMBOLS {}\n\n", codebook.len()));
+
+		header.push_str("/* Each code is the low `length` bits of `code`, first bit most significant */\n");
+		header.push_str(&format!("static const struct {{\n\tuint32_t code;\n\tuint8_t length;\n}} {name}_codes[{upper}_SYMBOLS] = {{\n"));
+		for (character, code, length) in &codebook {
+			header.push_str(&format!("\t{{0x{code:X}u, {length}}}, /* U+{:04X} */\n", u32::from(*character)));
+		}
+		header.push_str("};\n\n");
+
+		header.push_str(&format!("/* The Unicode codepoint each entry of {name}_codes encodes */\n"));
+		header.push_str(&format!("static const uint32_t {name}_symbols[{upper}_SYMBOLS] = {{\n"));
+		for (character, _, _) in &codebook {
+			header.push_str(&format!("\t0x{:X}u,\n", u32::from(*character)));
+		}
+		header.push_str(&format!("}};\n\n#endif /* {upper}_H */\n"));
+
+		Ok(header)
+	}
+
+	/// Lists the symbols whose codes differ between this tree and another
+	///
+	/// Each entry has a symbol, its code in this tree and its code in the
+	/// other, with None where a tree has no code for it. Symbols are in
+	/// codepoint order. Comparing `code_lengths` instead shows whether two trees
+	/// differ only by which codes of each length went to which symbols.
+	pub fn codebook_diff(&self, other: &HuffmanTree) -> Vec<(char, Option<Bits>, Option<Bits>)> {
+		let mut symbols: Vec<char> = self.lookup_table.iter()
+			.chain(other.lookup_table.iter())
+			.map(|(character, _)| character)
+			.collect();
+		symbols.sort_unstable();
+		symbols.dedup();
+
+		symbols.into_iter()
+			.map(|character| (character, self.lookup_table.get(character), other.lookup_table.get(character)))
+			.filter(|(_, code, other_code)| code != other_code)
+			.map(|(character, code, other_code)| (character, code.cloned(), other_code.cloned()))
+			.collect()
+	}
+
+	/// Writes the tree as readable text that `from_text` can read back
+	///
+	/// This is the same format as `dump_codebook`, one `symbol<TAB>code` line
+	/// per symbol, so a trained codebook can be checked into version control,
+	/// diffed and edited by hand.
+	pub fn to_text(&self) -> String {
+		self.dump_codebook()
+	}
+
+	/// Construct a Huffman tree from text written by `to_text`
+	///
+	/// Symbols use the escapes from `escape_symbol`, and blank lines are
+	/// ignored. This will error on a malformed line, reporting its line number,
+	/// and in the same cases as `from_codes`.
+	pub fn from_text(text: &str) -> Result<Self> {
+		let mut codes = Vec::new();
+
+		for (number, line) in text.lines().enumerate() {
+			if line.is_empty() {
+				continue;
+			}
+
+			let code = parse_code_line(line)
+				.with_context(|| format!("Invalid code on line {}", number + 1))?;
+			codes.push(code);
+		}
+
+		Self::from_codes(&codes)
+	}
+
+	/// Loads a tree cached for some text, or builds one and caches it
+	///
+	/// The cache file starts with a line holding the CRC-32 and length of the
+	/// text it was built from, followed by the tree in the `to_text` format.
+	/// The cached tree is used only when that line matches `example_text`, so
+	/// a cache left over from different text is stale and gets rebuilt.
+	/// Hashing the text is much cheaper than counting it and building a tree.
+	/// A missing or unreadable cache is rebuilt the same way.
+	///
+	/// This will error if the tree cannot be built or the cache cannot be
+	/// written.
+	pub fn load_or_build<P: AsRef<Path>>(cache_path: P, example_text: &str) -> Result<Self> {
+		let header = cache_header(example_text);
+
+		let cached = fs::read_to_string(&cache_path).ok()
+			.and_then(|contents| {
+				let codebook = contents.strip_prefix(&header)?.strip_prefix('\n')?;
+				Self::from_text(codebook).ok()
+			});
+		if let Some(tree) = cached {
+			return Ok(tree);
+		}
+
+		let tree = Self::new(example_text)?;
+		fs::write(&cache_path, format!("{header}\n{}", tree.to_text()))
+			.context("Failed to write tree cache")?;
+
+		Ok(tree)
+	}
+
+	/// Lists every symbol with its frequency in some text and its code length
+	///
+	/// The list is sorted from most to least frequent, with ties broken by
+	/// symbol. Frequent symbols should have the shortest codes, so reading down
+	/// the list shows whether the tree fits the text. Symbols missing from the
+	/// text have a frequency of 0.
+	pub fn dump_sorted_by_frequency(&self, text: &str) -> Vec<(char, usize, usize)> {
+		let frequencies = get_letter_frequencies(text);
+
+		let mut dump: Vec<_> = self.code_lengths().into_iter()
+			.map(|(character, length)| {
+				let frequency = frequencies.get(&character).copied().unwrap_or(0);
+				(character, frequency, length)
+			})
+			.collect();
+
+		dump.sort_unstable_by_key(|(character, frequency, _)| (std::cmp::Reverse(*frequency), *character));
+		dump
+	}
+
+	/// Draws the tree as indented text, like the output of the `tree` command
+	///
+	/// The root is drawn as `.`, and every other node as the code of the path
+	/// to it, with leaves followed by their symbol in quotes and escaped with
+	/// `escape_symbol`. Left branches come first. A tree with only one symbol
+	/// is a single leaf with no path, so it is drawn as just that symbol. Nodes
+	/// are visited with an explicit stack, so deep trees are safe.
+	pub fn to_ascii_tree(&self) -> String {
+		let left_bit = self.settings.convention.left_bit();
+		let mut output = String::new();
+		let mut stack = vec![(&self.root, String::new(), String::new(), None)];
+
+		while let Some((node, code, indent, is_last)) = stack.pop() {
+			let (branch, child_indent) = match is_last {
+				None => ("", indent.clone()),
+				Some(true) => ("└── ", format!("{indent}    ")),
+				Some(false) => ("├── ", format!("{indent}│   ")),
+			};
+
+			let label = match (node.value, code.is_empty()) {
+				(Some(value), true) => format!("'{}'", escape_symbol(value)),
+				(Some(value), false) => format!("{code} '{}'", escape_symbol(value)),
+				(None, true) => String::from("."),
+				(None, false) => code.clone(),
+			};
+			output.push_str(&format!("{indent}{branch}{label}\n"));
+
+			let children: Vec<_> = [(&node.left_child, left_bit), (&node.right_child, !left_bit)].into_iter()
+				.filter_map(|(child, bit)| Some((child.as_ref()?, bit)))
+				.collect();
+			for (index, (child, bit)) in children.iter().enumerate().rev() {
+				let child_code = format!("{code}{}", u8::from(*bit));
+				stack.push((child, child_code, child_indent.clone(), Some(index + 1 == children.len())));
+			}
+		}
+
+		output
+	}
+
+	/// Lists every branch of the tree as a parent index, child index and bit
+	///
+	/// Nodes are numbered in preorder with left branches first, the same order
+	/// `to_ascii_tree` draws them in, so the root is 0 and node `n` is line `n`
+	/// of that drawing. The bit is the one written when the branch is taken.
+	/// Edges are sorted by child index, and a node with no outgoing edges is a
+	/// leaf. A tree with only one symbol has no edges. This is meant for
+	/// building custom visualizations and analyses of the tree's shape.
+	pub fn edges(&self) -> Vec<(usize, usize, bool)> {
+		let left_bit = self.settings.convention.left_bit();
+		let mut edges = Vec::new();
+		let mut stack = vec![(&self.root, None)];
+		let mut next_index = 0;
+
+		while let Some((node, parent)) = stack.pop() {
+			let index = next_index;
+			next_index += 1;
+
+			if let Some((parent, bit)) = parent {
+				edges.push((parent, index, bit));
+			}
+
+			for (child, bit) in [(&node.right_child, !left_bit), (&node.left_child, left_bit)] {
+				if let Some(child) = child {
+					stack.push((child, Some((index, bit))));
+				}
+			}
+		}
+
+		edges
+	}
+
+	/// Writes the codebook as text, one `symbol<TAB>code` line per symbol
+	///
+	/// Symbols are in codepoint order and escaped with `escape_symbol`, so
+	/// whitespace and control characters stay readable and on their own line.
+	pub fn dump_codebook(&self) -> String {
+		self.lookup_table.iter()
+			.map(|(character, code)| format!("{}\t{code}\n", escape_symbol(character)))
+			.collect()
+	}
+
+	/// Encodes a string to a Bits object using this Huffman tree
+	///
+	/// This uses the lookup table learned from the example text provided at
+	/// construction. This function will fail if any characters being encoded
+	/// were not in that initial text.
+	///
+	/// An empty string is valid input. It encodes to empty bits, or to just the
+	/// end of stream code for a tree made with `new_with_eof`, and either
+	/// decodes back to an empty string.
+	pub fn encode(&self, string: &str) -> Result<Bits> {
+		let mut encoded_string = Bits::new();
+		self.encode_into(string, &mut encoded_string)?;
+
+		Ok(encoded_string)
+	}
+
+	/// Encodes a string, reusing an earlier encoding from a cache
+	///
+	/// Protocols often send the same short messages over and over. The caller
+	/// owns the cache and decides when to clear it. A message is only encoded
+	/// the first time it is seen, and every call returns a reference into the
+	/// cache, so nothing is cloned. Failed encodes are not cached.
+	pub fn encode_cached<'a>(&self, string: &str, cache: &'a mut HashMap<String, Bits>) -> Result<&'a Bits> {
+		if !cache.contains_key(string) {
+			let encoded_string = self.encode(string)?;
+			cache.insert(string.to_string(), encoded_string);
+		}
+
+		Ok(&cache[string])
+	}
+
+	/// Encodes a string and also returns the number of characters encoded
+	///
+	/// Decoders that have to deal with padding often need the symbol count, and
+	/// this keeps it consistent with what was actually encoded.
+	pub fn encode_with_count(&self, string: &str) -> Result<(Bits, usize)> {
+		let encoded_string = self.encode(string)?;
+		Ok((encoded_string, string.chars().count()))
+	}
+
+	/// Encodes many independent messages with this one tree
+	///
+	/// Each message gets its own Bits, exactly as `encode` would give it, so
+	/// protocols that send many short frames with a shared codebook can send
+	/// them separately. Every message is encoded into one reused buffer and
+	/// then copied out at its exact size, which saves growing a new Bits from
+	/// empty for each. This fails on the first message `encode` would fail on,
+	/// reporting its index.
+	pub fn encode_batch(&self, messages: &[&str]) -> Result<Vec<Bits>> {
+		let mut buffer = Bits::new();
+
+		messages.iter()
+			.enumerate()
+			.map(|(index, message)| {
+				buffer.clear();
+				self.encode_into(message, &mut buffer)
+					.with_context(|| format!("Failed to encode message {index}"))?;
+
+				Ok(Bits::from_bool_slice(buffer.as_slice()))
+			})
+			.collect()
+	}
+
+	/// Encodes a string onto the end of an existing Bits object
+	///
+	/// Reusing one buffer across calls avoids allocating for every message.
+	/// If a character cannot be encoded, the bits before it are left in `out`.
+	pub fn encode_into(&self, string: &str, out: &mut Bits) -> Result<()> {
+		let string = self.normalize(string);
+		self.check_reserved_symbols(&string)?;
+		self.encode_symbols(&string, out)?;
+		self.append_eof(out);
+
+		Ok(())
+	}
+
+	/// Encodes a string without the end of stream symbol, escaping rare
+	/// symbols if this tree does
+	fn encode_symbols(&self, string: &str, out: &mut Bits) -> Result<()> {
+		if self.escape().is_none() {
+			return Self::encode_with_tables(&self.lookup_table, &self.byte_table, string, out);
+		}
+
+		for character in string.chars() {
+			self.append_code(character, out)?;
+		}
+
+		Ok(())
+	}
+
+	/// Appends the code for a character, escaping it when it has no code
+	///
+	/// This will fail if the character has no code and this tree does not
+	/// escape symbols.
+	fn append_code(&self, character: char, out: &mut Bits) -> Result<()> {
+		if let Some(code) = self.try_code(character) {
+			out.append(code);
+			return Ok(());
+		}
+
+		let escape_code = self.escape()
+			.and_then(|escape| self.try_code(escape))
+			.context("Character not found in lookup table")?;
+
+		out.append(escape_code);
+		for index in (0..ESCAPE_CODEPOINT_BITS).rev() {
+			out.add((character as u32 >> index) & 1 == 1);
+		}
+
+		Ok(())
+	}
+
+	/// Gets the number of bits a character encodes to, counting any escape
+	fn code_len(&self, character: char) -> Result<usize> {
+		let character = self.settings.normalize.map_or(character, |normalize| normalize(character));
+		if let Some(code) = self.try_code(character) {
+			return Ok(code.len());
+		}
+
+		self.escape()
+			.and_then(|escape| self.try_code(escape))
+			.map(|escape_code| escape_code.len() + ESCAPE_CODEPOINT_BITS)
+			.context("Character not found in lookup table")
+	}
+
+	/// Reads an escaped codepoint that follows the escape symbol's code
+	///
+	/// This will fail if the bits end first or the codepoint is not a valid
+	/// character.
+	pub(super) fn read_escaped(bits: &mut impl Iterator<Item = bool>) -> Result<char> {
+		let mut codepoint = 0;
+
+		for _ in 0..ESCAPE_CODEPOINT_BITS {
+			let bit = bits.next()
+				.context("Bits end partway through an escaped codepoint")?;
+			codepoint = codepoint << 1 | u32::from(bit);
+		}
+
+		char::from_u32(codepoint)
+			.with_context(|| format!("Escaped codepoint {codepoint:#X} is not a valid character"))
+	}
+
+	/// Gets the end of stream symbol, if this tree has one
+	pub(super) fn eof(&self) -> Option<char> {
+		self.settings.eof.then_some(EOF_SYMBOL)
+	}
+
+	/// Gets the escape symbol, if this tree escapes rare symbols
+	pub(super) fn escape(&self) -> Option<char> {
+		self.settings.rare_threshold.map(|_| ESCAPE_SYMBOL)
+	}
+
+	/// Checks that text does not contain this tree's escape or end of stream
+	/// symbols
+	fn check_reserved_symbols(&self, string: &str) -> Result<()> {
+		match (self.escape(), self.eof()) {
+			(Some(escape), _) if string.contains(escape) => Err(anyhow!("Cannot encode U+FFFE, which is reserved for escapes")),
+			(_, Some(eof)) if string.contains(eof) => Err(anyhow!("Cannot encode U+FFFF, which is reserved for the end of stream")),
+			_ => Ok(())
+		}
+	}
+
+	/// Passes every character of a string through the tree's normalization,
+	/// if it has one
+	fn normalize<'a>(&self, string: &'a str) -> Cow<'a, str> {
+		match self.settings.normalize {
+			Some(normalize) => Cow::Owned(string.chars().map(normalize).collect()),
+			None => Cow::Borrowed(string),
+		}
+	}
+
+	/// Appends the code of the end of stream symbol, if this tree has one
+	fn append_eof(&self, out: &mut Bits) {
+		if let Some(code) = self.eof().and_then(|eof| self.lookup_table.get(eof)) {
+			out.append(code);
+		}
+	}
+
+	/// Encodes a string in parallel by splitting it into chunks
+	///
+	/// The chunks are split on character boundaries and encoded on the rayon
+	/// thread pool. Codes are prefix-free, so joining the encoded chunks gives
+	/// the same bits as `encode`. Small inputs are not worth splitting and are
+	/// encoded as a single chunk.
+	#[cfg(feature = "rayon")]
+	pub fn encode_parallel(&self, string: &str) -> Result<Bits> {
+		use rayon::prelude::*;
+
+		let string = self.normalize(string);
+		self.check_reserved_symbols(&string)?;
+		let encoded_chunks = split_chunks(&string, PARALLEL_CHUNK_BYTES)
+			.par_iter()
+			.map(|chunk| {
+				let mut encoded_chunk = Bits::new();
+				self.encode_symbols(chunk, &mut encoded_chunk)?;
+				Ok(encoded_chunk)
+			})
+			.collect::<Result<Vec<Bits>>>()?;
+
+		let mut encoded_string = Bits::new();
+		encoded_string.reserve(encoded_chunks.iter().map(Bits::len).sum());
+		encoded_chunks.iter().for_each(|chunk| encoded_string.append(chunk));
+		self.append_eof(&mut encoded_string);
+
+		Ok(encoded_string)
+	}
+
+	/// Encodes a string using the lookup tables, taking the ASCII fast path
+	/// when possible
+	fn encode_with_tables(lookup_table: &LookupTable<Bits>, byte_table: &ByteTable, string: &str, out: &mut Bits) -> Result<()> {
+		if string.is_ascii() {
+			return Self::encode_byte_symbols(byte_table, string.as_bytes(), out);
+		}
+
+		Self::encode_general(lookup_table, string, out)
+	}
+
+	/// Encodes binary data using a tree trained on bytes
+	///
+	/// Each byte is looked up in a fixed 256 entry table, so there are no UTF-8
+	/// concerns. This will fail if any byte value was not in the training data.
+	pub fn encode_bytes(&self, data: &[u8]) -> Result<Bits> {
+		let mut encoded_data = Bits::new();
+		Self::encode_byte_symbols(&self.byte_table, data, &mut encoded_data)?;
+		self.append_eof(&mut encoded_data);
+
+		Ok(encoded_data)
+	}
+
+	/// Encodes integer IDs using a tree trained on IDs
+	///
+	/// This will fail if any ID was not in the training data.
+	pub fn encode_ids(&self, ids: &[u32]) -> Result<Bits> {
+		let mut encoded_ids = Bits::new();
+
+		for id in ids {
+			let encoded_id = self.try_code(id_to_symbol(*id)?)
+				.with_context(|| format!("ID {id} not found in lookup table"))?;
+			encoded_ids.append(encoded_id);
+		}
+		self.append_eof(&mut encoded_ids);
+
+		Ok(encoded_ids)
+	}
+
+	/// Encodes a string straight into a bit writer
+	///
+	/// Codes are packed into bytes as they are produced, so the whole message
+	/// never has to be held as a Bits object. Call `finish` on the writer once
+	/// everything has been encoded.
+	pub fn encode_to_writer<W: Write>(&self, string: &str, writer: &mut BitWriter<W>) -> Result<()> {
+		let string = self.normalize(string);
+		self.check_reserved_symbols(&string)?;
+
+		let mut escaped = Bits::new();
+		for character in string.chars().chain(self.eof()) {
+			match self.try_code(character) {
+				Some(encoded_character) => writer.write_bits(encoded_character)?,
+				None => {
+					escaped.clear();
+					self.append_code(character, &mut escaped)?;
+					writer.write_bits(&escaped)?;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Encodes a string straight into packed bytes
+	///
+	/// Codes are written most significant bit first into a byte buffer with a
+	/// running bit cursor, so no intermediate Bits object is built. The bytes
+	/// match `encode` followed by `to_bytes`, and the bit count is returned
+	/// alongside them since the last byte may be padded with zeros.
+	///
+	/// When every code fits in 32 bits, codes come from a table of packed
+	/// integers and are shifted in whole rather than bit by bit. Trees that
+	/// escape rare symbols encode to Bits first instead.
+	pub fn encode_packed(&self, string: &str) -> Result<(Vec<u8>, usize)> {
+		if self.escape().is_some() {
+			let bits = self.encode(string)?;
+			return Ok((bits.to_bytes(), bits.len()));
+		}
+
+		let string = self.normalize(string);
+		self.check_reserved_symbols(&string)?;
+
+		let symbols = string.chars().chain(self.eof());
+		match &self.packed_table {
+			Some(packed_table) => Self::encode_packed_codes(packed_table, symbols, string.len()),
+			None => self.encode_packed_bits(symbols, string.len()),
+		}
+	}
+
+	/// Encodes a string with every symbol's code starting on a byte boundary
+	///
+	/// Each code is padded with zeros out to a whole number of bytes. When
+	/// every code is at most 8 bits, byte `i` holds symbol `i`, so any symbol
+	/// can be found and decoded on its own with `decode_byte_aligned`. This is
+	/// for indexes that need random access, not general compression: every
+	/// symbol costs at least a full byte, which is usually larger than the
+	/// UTF-8 text itself for ASCII.
+	pub fn encode_byte_aligned(&self, string: &str) -> Result<Vec<u8>> {
+		let string = self.normalize(string);
+		self.check_reserved_symbols(&string)?;
+
+		let mut bits = Bits::new();
+		for character in string.chars().chain(self.eof()) {
+			self.append_code(character, &mut bits)?;
+			for _ in 0..bits.pad_bits() {
+				bits.add(false);
+			}
+		}
+
+		Ok(bits.to_bytes())
+	}
+
+	/// Decodes bytes made by `encode_byte_aligned` back into a string
+	///
+	/// One code is read at a time, skipping the padding after it to the next
+	/// byte boundary. This fails in the same cases as `decode`.
+	pub fn decode_byte_aligned(&self, bytes: &[u8]) -> Result<String> {
+		let bits = Bits::from_bytes(bytes, bytes.len() * 8)?;
+		let mut decoded = String::new();
+		let mut position = 0;
+
+		while position < bits.len() {
+			let (character, length) = self.walk_symbol(&bits, position)?;
+			position += length;
+
+			if Some(character) == self.eof() {
+				break;
+			}
+
+			if Some(character) == self.escape() {
+				decoded.push(Self::read_escaped(&mut bits.iter().skip(position))?);
+				position += ESCAPE_CODEPOINT_BITS;
+			} else {
+				decoded.push(character);
+			}
+
+			position = position.next_multiple_of(8);
+		}
+
+		Ok(decoded)
+	}
+
+	/// Packs symbols using the packed code table
+	///
+	/// Codes collect in a 64-bit accumulator that holds under 8 bits between
+	/// codes, so a 32-bit code always fits before whole bytes are flushed.
+	fn encode_packed_codes(packed_table: &LookupTable<PackedCode>, symbols: impl Iterator<Item = char>, capacity: usize) -> Result<(Vec<u8>, usize)> {
+		let mut bytes = Vec::with_capacity(capacity);
+		let mut accumulator = 0u64;
+		let mut pending = 0;
+		let mut num_bits = 0;
+
+		for character in symbols {
+			let code = packed_table.get(character)
+				.context("Character not found in lookup table")?;
+
+			accumulator = accumulator << code.len | u64::from(code.bits);
+			pending += code.len as usize;
+			num_bits += code.len as usize;
+
+			while pending >= 8 {
+				pending -= 8;
+				bytes.push((accumulator >> pending) as u8);
+			}
+		}
+
+		if pending > 0 {
+			bytes.push((accumulator << (8 - pending)) as u8);
+		}
+
+		Ok((bytes, num_bits))
+	}
+
+	/// Packs symbols one bit at a time using the Bits codes
+	fn encode_packed_bits(&self, symbols: impl Iterator<Item = char>, capacity: usize) -> Result<(Vec<u8>, usize)> {
+		let mut bytes = Vec::with_capacity(capacity);
+		let mut current = 0u8;
+		let mut num_bits = 0;
+
+		for character in symbols {
+			let encoded_character = self.encode_character(&character)?;
+
+			for bit in encoded_character.iter() {
+				current = current << 1 | u8::from(bit);
+				num_bits += 1;
+
+				if num_bits % 8 == 0 {
+					bytes.push(current);
+					current = 0;
+				}
+			}
+		}
+
+		if num_bits % 8 != 0 {
+			bytes.push(current << (8 - num_bits % 8));
+		}
+
+		Ok((bytes, num_bits))
+	}
+
+	/// Encodes bytes as the characters U+0000 to U+00FF using the byte table
+	///
+	/// For pure ASCII text this gives the same bits as encoding its characters.
+	fn encode_byte_symbols(byte_table: &ByteTable, bytes: &[u8], out: &mut Bits) -> Result<()> {
+		for byte in bytes {
+			let encoded_character = byte_table[*byte as usize].as_ref()
+				.context("Character not found in lookup table")?;
+			out.append(encoded_character);
+		}
+
+		Ok(())
+	}
+
+	/// Encodes any text one character at a time using the full lookup table
+	fn encode_general(lookup_table: &LookupTable<Bits>, string: &str, out: &mut Bits) -> Result<()> {
+		for character in string.chars() {
+			let encoded_character = lookup_table.get(character)
+				.context("Character not found in lookup table")?;
+			out.append(encoded_character);
+		}
+
+		Ok(())
+	}
+
+	/// Checks whether a character has a code in this tree
+	///
+	/// This reads the lookup table directly, so it is a cheap way to filter
+	/// input before encoding it.
+	pub fn can_encode(&self, character: char) -> bool {
+		self.try_code(character).is_some()
+	}
+
+	/// Gets the code for a character packed into an integer, with its length
+	///
+	/// The code is the low `length` bits of the integer, with its first bit the
+	/// most significant, ready to be shifted into an output buffer without any
+	/// allocation. This gives None for a character with no code or a code
+	/// longer than 64 bits.
+	pub fn code_packed(&self, character: char) -> Option<(u64, u8)> {
+		let code = self.lookup_table.get(character)?;
+		if code.len() > 64 {
+			return None;
+		}
+
+		let packed = code.iter().fold(0, |packed, bit| packed << 1 | u64::from(bit));
+		Some((packed, code.len() as u8))
+	}
+
+	/// Encodes a single character to a Bits object using this Huffman tree
+	///
+	/// This uses the lookup table learned from the example text provided at
+	/// construction. This function will fail if the character being encoded was
+	/// not in that initial text.
+	fn encode_character(&self, character: &char) -> Result<&Bits> {
+		self.try_code(*character)
+			.context("Character not found in lookup table")
+	}
+
+	/// Gets the code for a character, or None if it has no code
+	///
+	/// Unlike `encode`, a miss builds no error, so nothing is allocated. This
+	/// suits hot loops that expect some characters to be missing, like
+	/// filtering input or falling back to an escape.
+	pub fn try_code(&self, character: char) -> Option<&Bits> {
+		self.lookup_table.get(character)
+	}
+
+	/// Decodes a Bits object back into a string using this Huffman tree
+	///
+	/// This will fail if the bits do not follow a path through the tree, or if
+	/// they end partway through a code.
+	///
+	/// Decoding uses a table-driven `Decoder`, built on the first call and
+	/// cached in the tree for every call after. It sits in a `OnceLock`, so a
+	/// shared `&HuffmanTree` can fill it in, and it is only ever built once.
+	/// Threads that race to decode first may each start building it, but all
+	/// of them end up using the same table.
+	pub fn decode(&self, bits: &Bits) -> Result<String> {
+		self.decoder().decode(self, bits)
+	}
+
+	/// Decodes many messages made by `encode_batch` back into strings
+	///
+	/// This fails on the first message `decode` would fail on, reporting its
+	/// index.
+	pub fn decode_batch(&self, messages: &[Bits]) -> Result<Vec<String>> {
+		messages.iter()
+			.enumerate()
+			.map(|(index, bits)| {
+				self.decode(bits)
+					.with_context(|| format!("Failed to decode message {index}"))
+			})
+			.collect()
+	}
+
+	/// Gets the cached table-driven decoder, building it if needed
+	fn decoder(&self) -> &Decoder {
+		self.decoder.get_or_init(|| Decoder::new(self.lookup_table.iter()))
+	}
+
+	/// Walks the tree to decode the single symbol starting at a position
+	///
+	/// Returns the symbol and the number of bits its code used.
+	pub(super) fn walk_symbol(&self, bits: &Bits, position: usize) -> Result<(char, usize)> {
+		let mut node = &self.root;
+
+		for (length, bit) in bits.iter().skip(position).enumerate() {
+			node = self.step(node, bit)
+				.context("Bits do not match any code in the tree")?;
+
+			if let Some(value) = node.value {
+				return Ok((value, length + 1));
+			}
+		}
+
+		Err(anyhow!("Bits end partway through a code"))
+	}
+
+	/// Decodes as many complete symbols as possible from the start of a Bits object
+	///
+	/// Returns the decoded string and the number of bits left over after the
+	/// last complete symbol. This never fails: a code cut off at the end, or
+	/// bits that do not follow a path through the tree, simply end the prefix.
+	/// When a message arrives in pieces, the leftover bits are the start of the
+	/// next symbol and can be kept to prepend to the next piece. Decoding also
+	/// stops after an EOF symbol, with any padding after it left over.
+	pub fn decode_prefix(&self, bits: &Bits) -> (String, usize) {
+		let mut decoded = String::new();
+		let mut position = 0;
+
+		while let Ok((character, length)) = self.walk_symbol(bits, position) {
+			if Some(character) == self.eof() {
+				position += length;
+				break;
+			}
+
+			if Some(character) == self.escape() {
+				let Ok(escaped) = Self::read_escaped(&mut bits.iter().skip(position + length)) else {
+					break;
+				};
+
+				decoded.push(escaped);
+				position += length + ESCAPE_CODEPOINT_BITS;
+			} else {
+				decoded.push(character);
+				position += length;
+			}
+		}
+
+		(decoded, bits.len() - position)
+	}
+
+	/// Decodes a Bits object back into a string, replacing bad codes
+	///
+	/// Every symbol in the tree is a `char`, so the output is always valid
+	/// UTF-8. Instead, any bits that do not follow a path through the tree, and
+	/// any incomplete code at the end, are replaced with U+FFFD. This mirrors
+	/// `String::from_utf8_lossy`, where `decode` mirrors `String::from_utf8`.
+	pub fn decode_lossy(&self, bits: &Bits) -> String {
+		self.decode_iter(bits)
+			.map(|result| result.unwrap_or(char::REPLACEMENT_CHARACTER))
+			.collect()
+	}
+
+	/// Decodes a Bits object, refusing to produce more than a set number of symbols
+	///
+	/// This guards services that decode untrusted input against output that is
+	/// far larger than expected. It fails in the same cases as `decode`, and as
+	/// soon as symbol `max_symbols + 1` is decoded.
+	pub fn decode_limited(&self, bits: &Bits, max_symbols: usize) -> Result<String> {
+		let mut decoded = String::new();
+
+		for (count, character) in self.decode_iter(bits).enumerate() {
+			let character = character?;
+			if count == max_symbols {
+				return Err(anyhow!("Decoded output exceeds {max_symbols} symbols"));
+			}
+
+			decoded.push(character);
+		}
+
+		Ok(decoded)
+	}
+
+	/// Decodes a Bits object back into binary data using a tree trained on bytes
+	///
+	/// This fails in the same cases as `decode`, and if a decoded symbol is not
+	/// a byte value.
+	pub fn decode_bytes_to_vec(&self, bits: &Bits) -> Result<Vec<u8>> {
+		self.decode_iter(bits)
+			.map(|character| {
+				u8::try_from(character?)
+					.context("Decoded symbol is not a byte")
+			})
+			.collect()
+	}
+
+	/// Decodes a Bits object back into integer IDs using a tree trained on IDs
+	///
+	/// This fails in the same cases as `decode`.
+	pub fn decode_ids(&self, bits: &Bits) -> Result<Vec<u32>> {
+		self.decode_iter(bits)
+			.map(|character| Ok(symbol_to_id(character?)))
+			.collect()
+	}
+
+	/// Lazily decodes a Bits object one character at a time
+	///
+	/// Invalid or truncated codes show up as errors in the stream, so callers
+	/// can process output without buffering the whole string.
+	pub fn decode_iter<'a>(&'a self, bits: &'a Bits) -> impl Iterator<Item = Result<char>> + 'a {
+		DecodeIter::new(self, bits.iter())
+	}
+
+	/// Starts a decoder that is fed bits one at a time
+	///
+	/// See `DecodeState`. Unlike `decode_iter`, the caller pushes each bit in as
+	/// it arrives instead of the decoder pulling from a source.
+	pub fn decode_state(&self) -> DecodeState<'_> {
+		DecodeState::new(self)
+	}
+
+	/// Decodes bits pulled one at a time from a bit reader
+	///
+	/// The tree is walked as bits arrive, so the encoded message never has to
+	/// be held as a Bits object. Give the reader the real bit length with
+	/// `BitReader::with_len` so padding in the final byte is not decoded.
+	pub fn decode_from_reader<R: Read>(&self, reader: &mut BitReader<R>) -> Result<String> {
+		let mut read_error = None;
+		let bits = std::iter::from_fn(|| {
+			reader.read_bit().unwrap_or_else(|error| {
+				read_error = Some(error);
+				None
+			})
+		});
+
+		let decoded: Result<String> = DecodeIter::new(self, bits).collect();
+		match read_error {
+			Some(error) => Err(error),
+			None => decoded
+		}
+	}
+
+	/// Gets the root node of the tree for inspecting its shape
+	///
+	/// This is only available with the `test-internals` feature and is not part
+	/// of the stable API. The layout of `Node` may change in any release.
+	#[cfg(feature = "test-internals")]
+	pub fn debug_tree(&self) -> &Arc<Node> {
+		&self.root
+	}
+
+	/// Gets the root node of the tree
+	pub(super) fn root(&self) -> &Arc<Node> {
+		&self.root
+	}
+
+	/// Follows one bit down the tree from a node
+	///
+	/// A lone leaf at the root stands for the left branch's bit.
+	pub(super) fn step<'a>(&'a self, node: &'a Arc<Node>, bit: bool) -> Option<&'a Arc<Node>> {
+		let left = bit == self.settings.convention.left_bit();
+
+		if node.value.is_some() {
+			return left.then_some(node);
+		}
+
+		match left {
+			true => node.left_child.as_ref(),
+			false => node.right_child.as_ref()
+		}
+	}
+
+	/// Traverse a subtree and extract its data into a lookup table
+	///
+	/// Nodes waiting to be visited are kept on an explicit stack rather than
+	/// the call stack, so even a tree as deep as a huge alphabet is safe.
+	///
+	/// * `left_bit` - The bit added to the code when going down a left branch
+	fn traverse(subtree: &Arc<Node>, table: &mut HashMap<char, Bits, BuildHasherDefault<FnvHasher>>, code: &Bits, left_bit: bool) {
+		let mut stack = vec![(subtree, code.clone())];
+
+		while let Some((node, code)) = stack.pop() {
+			if let Some(value) = node.value {
+				table.insert(value, code);
+				continue;
+			}
+
+			for (child, bit) in [(&node.right_child, !left_bit), (&node.left_child, left_bit)] {
+				if let Some(child) = child {
+					let mut child_code = code.clone();
+					child_code.add(bit);
+					stack.push((child, child_code));
+				}
+			}
+		}
+	}
+
+
+	/// Initializes a sorted table of leaf nodes from a hash map of character frequencies
+	fn init_table(frequencies: Frequencies, tie_break: TieBreak) -> NodeTable {
+		let mut table: Vec<(char, usize)> = frequencies.into_iter().collect();
+		tie_break.sort(&mut table);
+
+		table.iter()
+			.map(|(character, num)| {
+				let node = Node::new_leaf(*character);
+				(*num, Arc::new(node))
+			})
+			.collect()
+	}
+
+	/// Finds a spot to insert an entry into a node table
+	///
+	/// The entry goes after any entries of equal weight, so ties between a new
+	/// parent and existing nodes always resolve the same way. Merging older
+	/// nodes first also keeps code lengths as even as possible.
+	fn insert_table_entry(table: &mut NodeTable, num: usize, node: Arc<Node>) {
+		let position = table.partition_point(|(n, _)| *n <= num);
+		table.insert(position, (num, node));
+	}
+}
+
+impl std::fmt::Debug for HuffmanTree {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let codes: Vec<(char, &Bits)> = self.lookup_table.iter().collect();
+
+		f.debug_struct("HuffmanTree")
+			.field("alphabet_size", &self.alphabet_size())
+			.field("depth", &self.depth())
+			.field("codes", &codes)
+			.finish()
+	}
+}
+
+/// Splits a string into chunks of about `size` bytes on character boundaries
+fn split_chunks(string: &str, size: usize) -> Vec<&str> {
+	let mut chunks = Vec::with_capacity(string.len() / size + 1);
+	let mut rest = string;
+
+	while rest.len() > size {
+		let mut end = size;
+		while !rest.is_char_boundary(end) {
+			end += 1;
+		}
+
+		let (chunk, remainder) = rest.split_at(end);
+		chunks.push(chunk);
+		rest = remainder;
+	}
+
+	chunks.push(rest);
+	chunks
+}
+
+/// Writes the first line of a tree cache, identifying the text it was built from
+fn cache_header(text: &str) -> String {
+	format!("# crc32 {:08x} bytes {}", crc32(text.as_bytes()), text.len())
+}
+
+/// Parses a single `symbol<TAB>code` line
+fn parse_code_line(line: &str) -> Result<(char, Bits)> {
+	let (symbol, code) = line.split_once('\t')
+		.context("Missing tab between symbol and code")?;
+
+	Ok((unescape_symbol(symbol)?, code.parse()?))
+}
+
+/// Generates a list of byte frequencies
+///
+/// Returns a map of byte values, as the characters U+0000 to U+00FF, and the
+/// number of times they appear
+fn get_byte_frequencies(data: &[u8]) -> Frequencies {
+	let mut counts = [0; 256];
+	data.iter().for_each(|byte| counts[*byte as usize] += 1);
+
+	(0..=u8::MAX)
+		.filter(|byte| counts[*byte as usize] > 0)
+		.map(|byte| (char::from(byte), counts[byte as usize]))
+		.collect()
+}
+
+/// Generates a list of ID frequencies
+///
+/// Returns a map of IDs, as the symbols from `id_to_symbol`, and the number of
+/// times they appear. This will error if an ID is too large for a symbol.
+fn get_id_frequencies(ids: &[u32]) -> Result<Frequencies> {
+	let mut frequencies = Frequencies::default();
+
+	for id in ids {
+		*frequencies.entry(id_to_symbol(*id)?).or_insert(0) += 1;
+	}
+
+	Ok(frequencies)
+}
+
+/// Generates a list of letter frequencies from a sample of a string
+///
+/// Blocks are counted whenever the running total of `sample_rate` passes 1,
+/// which spreads the sample evenly through the text. Symbols found only in
+/// skipped blocks are given a count of 1.
+fn get_sampled_frequencies(string: &str, sample_rate: f64) -> Frequencies {
+	let mut frequencies = Frequencies::default();
+	let mut seen_ascii = [false; 128];
+	let mut credit = 0.0;
+
+	for block in split_chunks(string, SAMPLE_BLOCK_BYTES) {
+		credit += sample_rate;
+
+		if credit >= 1.0 {
+			credit -= 1.0;
+			for (character, count) in get_letter_frequencies(block) {
+				*frequencies.entry(character).or_insert(0) += count;
+			}
+		} else if block.is_ascii() {
+			block.bytes().for_each(|byte| seen_ascii[byte as usize] = true);
+		} else {
+			block.chars().for_each(|character| {
+				frequencies.entry(character).or_insert(1);
+			});
+		}
+	}
+
+	for (byte, _) in seen_ascii.iter().enumerate().filter(|(_, seen)| **seen) {
+		frequencies.entry(char::from(byte as u8)).or_insert(1);
+	}
+
+	frequencies
+}
+
+/// Generates a list of letter frequencies
+///
+/// Returns a map of characters and the number of times they appear
+pub(crate) fn get_letter_frequencies(string: &str) -> Frequencies {
+	let mut frequencies = FnvHashMap::with_capacity_and_hasher(100, Default::default());
+
+	for character in string.chars() {
+		let entry = frequencies.get_mut(&character);
+
+		match entry {
+			Some(e) => *e += 1,
+			None => {frequencies.insert(character, 1);}
+		};
+	}
+
+	frequencies
+}
+
+mod benchmarks {
+
+	#[allow(unused)]
+	use test::Bencher;
+	#[allow(unused)]
+	use test::black_box;
+	#[allow(unused)]
+	use super::HuffmanTree;
+	#[allow(unused)]
+	use std::collections::HashMap;
+
+	// Construct a new tree
+	#[bench]
+	fn new(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+
+		b.iter(|| HuffmanTree::new(&text));
+	}
+
+	// Construct a tree and encode its training text in one call
+	#[bench]
+	fn new_and_encode(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+
+		let (tree, bits) = HuffmanTree::new_and_encode(&text)
+			.expect("Failed to build and encode");
+		assert_eq!(bits, HuffmanTree::new(&text).and_then(|tree| tree.encode(&text)).expect("Failed to encode message"));
+		assert_eq!(bits.capacity(), bits.len());
+		assert_eq!(tree.decode(&bits).expect("Failed to decode message"), text);
+		assert!(HuffmanTree::new_and_encode("").is_err());
+
+		b.bytes = text.len() as u64;
+		b.iter(|| HuffmanTree::new_and_encode(black_box(&text)));
+	}
+
+	// Construct a tree then encode its training text in separate calls
+	#[bench]
+	fn new_then_encode(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+
+		b.bytes = text.len() as u64;
+		b.iter(|| HuffmanTree::new(black_box(&text)).and_then(|tree| tree.encode(&text)));
+	}
+
+	// Construct a tree with seeded tie-breaking
+	#[bench]
+	fn new_with_tiebreak(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tie_break = super::TieBreak::Seeded(660);
+		let first = HuffmanTree::new_with_tiebreak(&text, tie_break)
+			.expect("Failed to build Huffman tree");
+		let second = HuffmanTree::new_with_tiebreak(&text, tie_break)
+			.expect("Failed to build Huffman tree");
+		assert_eq!(first.code_lengths(), second.code_lengths());
+
+		b.iter(|| HuffmanTree::new_with_tiebreak(&text, tie_break));
+	}
+
+	// Construct the built-in English tree
+	#[bench]
+	fn english(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = HuffmanTree::english();
+
+		let bits = tree.encode(&text).expect("Failed to encode message");
+		assert_eq!(tree.decode(&bits).expect("Failed to decode message"), text);
+		assert!(bits.len() < text.len() * 8);
+		assert_eq!(HuffmanTree::english().code_lengths(), tree.code_lengths());
+		assert!(tree.encode("café").is_err());
+
+		b.iter(HuffmanTree::english);
+	}
+
+	// Retrain an existing tree
+	#[bench]
+	fn retrain(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let mut tree = HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		b.iter(|| tree.retrain(&text));
+	}
+
+	// Compute the encoded size from frequencies
+	#[bench]
+	fn total_encoded_bits(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let frequencies = super::get_letter_frequencies(&text);
+
+		let total_bits = tree.total_encoded_bits(&frequencies).expect("Failed to count bits");
+		assert_eq!(total_bits, tree.encode(&text).expect("Failed to encode message").len());
+		assert!(tree.total_encoded_bits(&super::get_letter_frequencies("😀")).is_err());
+
+		for message in ["", "e", "the quick brown fox", &text[..text.len() / 3]] {
+			let counts = super::get_letter_frequencies(message);
+			assert_eq!(tree.total_encoded_bits(&counts).expect("Failed to count bits"), tree.encode(message).expect("Failed to encode message").len());
+		}
+
+		b.iter(|| tree.total_encoded_bits(&frequencies));
+	}
+
+	// Estimate encoded bits from counts in a standard hash map
+	#[bench]
+	fn estimated_bits_for(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		let mut counts: HashMap<char, usize> = HashMap::new();
+		for character in text.chars() {
+			*counts.entry(character).or_default() += 1;
+		}
+		assert_eq!(tree.estimated_bits_for(&counts), tree.encode(&text).expect("Failed to encode message").len());
+		assert_eq!(tree.estimated_bits_for(&HashMap::new()), 0);
+
+		let eof_tree = super::HuffmanTree::new_with_eof(&text).expect("Failed to build Huffman tree");
+		assert_eq!(eof_tree.estimated_bits_for(&counts), eof_tree.encode(&text).expect("Failed to encode message").len());
+
+		let missing = HashMap::from([('😀', 1)]);
+		assert!(std::panic::catch_unwind(|| tree.estimated_bits_for(&missing)).is_err());
+
+		b.iter(|| tree.estimated_bits_for(black_box(&counts)));
+	}
+
+	// Export the codebook as packed codes
+	#[bench]
+	fn export_codebook(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		let codebook = tree.export_codebook().expect("Failed to export codebook");
+		assert!(codebook.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+		let codes: Vec<(char, super::Bits)> = codebook.iter()
+			.map(|(character, bits, len)| (*character, format!("{bits:0len$b}", len = *len as usize).parse().unwrap()))
+			.collect();
+		let rebuilt = super::HuffmanTree::from_codes(&codes)
+			.expect("Failed to build Huffman tree");
+		assert_eq!(rebuilt.encode(&text).unwrap(), tree.encode(&text).unwrap());
+
+		let deep: Vec<(char, usize)> = ('a'..='z').chain('A'..='J').enumerate()
+			.map(|(index, symbol)| (symbol, (index + 1).min(35)))
+			.collect();
+		assert!(super::HuffmanTree::from_code_lengths(&deep).unwrap().export_codebook().is_err());
+
+		b.iter(|| tree.export_codebook());
+	}
+
+	// Refuse codebooks deep enough to overflow the stack
+	#[bench]
+	fn from_codes_max_length(b: &mut Bencher) {
+		let codes = |length: usize| vec![
+			('a', "0".repeat(length).parse::<super::Bits>().expect("Failed to parse bits")),
+			('b', "1".parse().expect("Failed to parse bits")),
+		];
+
+		let deepest = super::HuffmanTree::from_codes(&codes(super::MAX_CODE_LENGTH))
+			.expect("Failed to build Huffman tree");
+		assert_eq!(deepest.depth(), super::MAX_CODE_LENGTH);
+		deepest.self_check("abba").expect("Failed self check");
+
+		assert!(super::HuffmanTree::from_codes(&codes(super::MAX_CODE_LENGTH + 1)).is_err());
+		assert!(super::HuffmanTree::from_codes(&codes(1_000_000)).is_err());
+		assert!(super::HuffmanTree::from_code_lengths(&[('a', 1), ('b', usize::MAX)]).is_err());
+		assert!(super::HuffmanTree::from_text(&format!("a\t{}\nb\t1\n", "0".repeat(1_000_000))).is_err());
+
+		b.iter(|| super::HuffmanTree::from_codes(black_box(&codes(super::MAX_CODE_LENGTH))));
+	}
+
+	// Write a codebook as a C header
+	#[bench]
+	fn to_c_header(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		let small = super::HuffmanTree::from_code_lengths(&[('a', 1), ('b', 2), ('é', 2)])
+			.expect("Failed to build Huffman tree");
+		assert_eq!(small.to_c_header("small_book").expect("Failed to write header"), "\
+#ifndef SMALL_BOOK_H
+#define SMALL_BOOK_H
+
+#include <stdint.h>
+
+#define SMALL_BOOK_SYMBOLS 3
+
+/* Each code is the low `length` bits of `code`, first bit most significant */
+static const struct {
+	uint32_t code;
+	uint8_t length;
+} small_book_codes[SMALL_BOOK_SYMBOLS] = {
+	{0x0u, 1}, /* U+0061 */
+	{0x2u, 2}, /* U+0062 */
+	{0x3u, 2}, /* U+00E9 */
+};
+
+/* The Unicode codepoint each entry of small_book_codes encodes */
+static const uint32_t small_book_symbols[SMALL_BOOK_SYMBOLS] = {
+	0x61u,
+	0x62u,
+	0xE9u,
+};
+
+#endif /* SMALL_BOOK_H */
+");
+
+		let header = tree.to_c_header("codebook").expect("Failed to write header");
+		assert_eq!(header.matches(" /* U+").count(), tree.alphabet_size());
+		for name in ["", "2fast", "my-book", "naïve"] {
+			assert!(tree.to_c_header(name).is_err());
+		}
+
+		b.iter(|| tree.to_c_header(black_box("codebook")));
+	}
+
+	// Write a tree as text and read it back
+	#[bench]
+	fn to_text(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		let written = tree.to_text();
+		let read = super::HuffmanTree::from_text(&written)
+			.expect("Failed to read tree");
+		assert!(read.codebook_diff(&tree).is_empty());
+		assert_eq!(read.to_text(), written);
+
+		let edited = super::HuffmanTree::from_text("\\n\t0\n\n\\u{1B}\t10\na\t11\n")
+			.expect("Failed to read tree");
+		assert_eq!(edited.code_lengths(), vec![('\n', 1), ('\u{1B}', 2), ('a', 2)]);
+
+		let error = super::HuffmanTree::from_text("a\t0\nb 1\n").expect_err("Read a malformed tree");
+		assert_eq!(error.to_string(), "Invalid code on line 2");
+		assert!(super::HuffmanTree::from_text("a\t0\nb\t01\n").is_err());
+
+		b.iter(|| super::HuffmanTree::from_text(&written));
+	}
+
+	// Compare the codebooks of two trees
+	#[bench]
+	fn codebook_diff(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let canonical = super::HuffmanTree::from_code_lengths(&tree.code_lengths())
+			.expect("Failed to build Huffman tree");
+
+		assert!(tree.codebook_diff(&tree).is_empty());
+		assert_eq!(canonical.code_lengths(), tree.code_lengths());
+
+		let small = super::HuffmanTree::from_code_lengths(&[('a', 1), ('b', 2), ('c', 2)]).unwrap();
+		let other = super::HuffmanTree::from_code_lengths(&[('a', 2), ('b', 2), ('d', 1)]).unwrap();
+		let code = |string: &str| Some(string.parse::<super::Bits>().unwrap());
+		assert_eq!(small.codebook_diff(&other), vec![
+			('a', code("0"), code("10")),
+			('b', code("10"), code("11")),
+			('c', code("11"), None),
+			('d', None, code("0")),
+		]);
+
+		b.iter(|| tree.codebook_diff(&canonical));
+	}
+
+	// Write the codebook as text
+	#[bench]
+	fn dump_codebook(b: &mut Bencher) {
+		let tree = super::HuffmanTree::from_code_lengths(&[('\n', 2), (' ', 2), ('a', 2), ('\u{7}', 3), ('\\', 3)])
+			.expect("Failed to build Huffman tree");
+
+		assert_eq!(tree.dump_codebook(), "\\u{7}\t110\n\\n\t00\n \t01\n\\\\\t111\na\t10\n");
+		b.iter(|| tree.dump_codebook());
+	}
+
+	// Encode a string
+	#[bench]
+	fn encode(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		b.bytes = text.len() as u64;
+		b.iter(|| tree.encode(&text));
+	}
+
+	// Encode a repeated message through a cache
+	#[bench]
+	fn encode_cached(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let message = &text[..64];
+
+		let mut cache = std::collections::HashMap::new();
+		let bits = tree.encode_cached(message, &mut cache).expect("Failed to encode message").clone();
+		assert_eq!(bits, tree.encode(message).expect("Failed to encode message"));
+		assert_eq!(tree.encode_cached(message, &mut cache).expect("Failed to encode message"), &bits);
+		assert!(tree.encode_cached("😀", &mut cache).is_err());
+		assert_eq!(cache.len(), 1);
+
+		b.iter(|| tree.encode_cached(message, &mut cache).map(|bits| bits.len()));
+	}
+
+	// Encode a string into a reused buffer
+	#[bench]
+	fn encode_into(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		let mut bits = super::Bits::new();
+		b.bytes = text.len() as u64;
+		b.iter(|| {
+			bits.clear();
+			tree.encode_into(&text, &mut bits)
+		});
+	}
+
+	// Encode a string into a bit writer
+	#[bench]
+	fn encode_to_writer(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		let mut bytes = Vec::new();
+		let mut writer = super::BitWriter::new(&mut bytes);
+		tree.encode_to_writer(&text, &mut writer).expect("Failed to encode message");
+		let num_bits = writer.finish().expect("Failed to finish writing");
+		let bits = tree.encode(&text).expect("Failed to encode message");
+		assert_eq!(num_bits, bits.len());
+		assert_eq!(bytes, bits.to_bytes());
+
+		b.bytes = text.len() as u64;
+		b.iter(|| {
+			let mut writer = super::BitWriter::new(std::io::sink());
+			tree.encode_to_writer(&text, &mut writer)?;
+			writer.finish()
+		});
+	}
+
+	// Decode a string from a bit reader
+	#[bench]
+	fn decode_from_reader(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let bits = tree.encode(&text)
+			.expect("Failed to encode message");
+		let bytes = bits.to_bytes();
+
+		b.bytes = text.len() as u64;
+		b.iter(|| {
+			let mut reader = super::BitReader::with_len(bytes.as_slice(), bits.len());
+			tree.decode_from_reader(&mut reader)
+		});
+	}
+
+	// Count the leaves of the tree through its root
+	#[cfg(feature = "test-internals")]
+	#[bench]
+	fn debug_tree(b: &mut Bencher) {
+		fn count_leaves(node: &super::Node) -> usize {
+			match (&node.left_child, &node.right_child) {
+				(None, None) => 1,
+				(left, right) => left.iter().chain(right).map(|child| count_leaves(child)).sum(),
+			}
+		}
+
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		assert_eq!(count_leaves(tree.debug_tree()), tree.alphabet_size());
+		b.iter(|| count_leaves(tree.debug_tree()));
+	}
+
+	// Share one tree between threads
+	#[bench]
+	fn encode_shared(b: &mut Bencher) {
+		fn assert_send_sync<T: Send + Sync>() {}
+		assert_send_sync::<super::HuffmanTree>();
+
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = std::sync::Arc::new(super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree"));
+
+		std::thread::scope(|scope| {
+			let workers: Vec<_> = (0..4)
+				.map(|_| scope.spawn(|| {
+					let encoded = tree.encode(&text).expect("Failed to encode message");
+					tree.decode(&encoded).expect("Failed to decode message")
+				}))
+				.collect();
+
+			for worker in workers {
+				assert_eq!(worker.join().expect("Worker panicked"), text);
+			}
+		});
+
+		b.iter(|| tree.encode(&text));
+	}
+
+	// Encode a large string in parallel
+	#[cfg(feature = "rayon")]
+	#[bench]
+	fn encode_parallel(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file")
+			.repeat(100);
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		assert_eq!(tree.encode_parallel(&text).expect("Failed to encode message"), tree.encode(&text).expect("Failed to encode message"));
+		b.bytes = text.len() as u64;
+		b.iter(|| tree.encode_parallel(&text));
+	}
+
+	// Encode a large string serially
+	#[bench]
+	fn encode_large(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file")
+			.repeat(100);
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		b.bytes = text.len() as u64;
+		b.iter(|| tree.encode(&text));
+	}
+
+	// Construct a tree from a sample of a large string
+	#[bench]
+	fn new_sampled(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file")
+			.repeat(100);
+		let exact = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let sampled = super::HuffmanTree::new_sampled(&text, 0.1)
+			.expect("Failed to build Huffman tree");
+
+		let exact_bits = exact.encode(&text).expect("Failed to encode message").len();
+		let sampled_bits = sampled.encode(&text).expect("Failed to encode message").len();
+		assert!(sampled_bits as f64 <= exact_bits as f64 * 1.01);
+		assert_eq!(sampled.alphabet_size(), exact.alphabet_size());
+
+		let rare = "a".repeat(100_000) + "é";
+		assert!(super::HuffmanTree::new_sampled(&rare, 0.01).unwrap().can_encode('é'));
+		assert!(super::HuffmanTree::new_sampled(&text, 0.0).is_err());
+		assert!(super::HuffmanTree::new_sampled(&text, f64::NAN).is_err());
+
+		b.iter(|| super::HuffmanTree::new_sampled(&text, 0.1));
+	}
+
+	// Construct a tree from all of a large string
+	#[bench]
+	fn new_large(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file")
+			.repeat(100);
+
+		b.iter(|| super::HuffmanTree::new(&text));
+	}
+
+	// Encode a string without the ASCII fast path
+	#[bench]
+	fn encode_general(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		let mut bits = super::Bits::new();
+		b.iter(|| {
+			bits.clear();
+			super::HuffmanTree::encode_general(&tree.lookup_table, &text, &mut bits)
+		});
+	}
+
+	// Encode text with high codepoints from a hash map table
+	#[bench]
+	fn encode_hashed(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file") + "😀";
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let bits = tree.encode(&text).expect("Failed to encode message");
+
+		for strategy in [super::LookupStrategy::DenseVec, super::LookupStrategy::HashMap] {
+			let other = super::HuffmanTree::new_with_lookup_strategy(&text, strategy)
+				.expect("Failed to build Huffman tree");
+			assert_eq!(other.encode(&text).expect("Failed to encode message"), bits);
+			assert_eq!(other.lookup_table_density(), tree.lookup_table_density());
+		}
+
+		let hashed = super::HuffmanTree::new_with_lookup_strategy(&text, super::LookupStrategy::HashMap)
+			.expect("Failed to build Huffman tree");
+		b.iter(|| hashed.encode(&text));
+	}
+
+	// Encode a string with the ASCII fast path
+	#[bench]
+	fn encode_ascii(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		let mut bits = super::Bits::new();
+		b.iter(|| {
+			bits.clear();
+			super::HuffmanTree::encode_byte_symbols(&tree.byte_table, text.as_bytes(), &mut bits)
+		});
+	}
+
+	// Decode by feeding in one bit at a time
+	#[bench]
+	fn decode_state(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let bits = tree.encode(&text).expect("Failed to encode message");
+
+		let mut state = tree.decode_state();
+		let mut decoded = String::new();
+		for bit in bits.iter() {
+			decoded.extend(state.feed(bit).expect("Failed to decode message"));
+		}
+		assert_eq!(decoded, text);
+		assert!(state.is_at_boundary());
+
+		let lone = super::HuffmanTree::new("a").expect("Failed to build Huffman tree");
+		let mut state = lone.decode_state();
+		assert_eq!(state.feed(false).unwrap(), Some('a'));
+		assert!(state.feed(true).is_err());
+
+		b.iter(|| {
+			let mut state = tree.decode_state();
+			bits.iter().filter_map(|bit| state.feed(bit).transpose()).collect::<anyhow::Result<String>>()
+		});
+	}
+
+	// Encode binary data
+	#[bench]
+	fn encode_bytes(b: &mut Bencher) {
+		let data: Vec<u8> = (0..10_000u32).map(|index| (index * index % 251) as u8).collect();
+		let tree = super::HuffmanTree::new_from_bytes(&data)
+			.expect("Failed to build Huffman tree");
+		let bits = tree.encode_bytes(&data)
+			.expect("Failed to encode data");
+
+		assert_eq!(tree.decode_bytes_to_vec(&bits).expect("Failed to decode data"), data);
+		b.iter(|| tree.encode_bytes(&data));
+	}
+
+	// Encode integer IDs
+	#[bench]
+	fn encode_ids(b: &mut Bencher) {
+		let ids: Vec<u32> = (0..10_000u32).map(|index| index * index % 997 + 0xD000).collect();
+		let tree = super::HuffmanTree::new_from_ids(&ids)
+			.expect("Failed to build Huffman tree");
+
+		let bits = tree.encode_ids(&ids).expect("Failed to encode IDs");
+		assert_eq!(tree.decode_ids(&bits).expect("Failed to decode IDs"), ids);
+		assert!(tree.encode_ids(&[7]).is_err());
+		assert!(super::HuffmanTree::new_from_ids(&[u32::MAX]).is_err());
+
+		b.iter(|| tree.encode_ids(&ids));
+	}
+
+	// Decode a string with the cached decoder
+	#[bench]
+	fn decode(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let bits = tree.encode(&text)
+			.expect("Failed to encode message");
+
+		assert_eq!(tree.decode(&bits).expect("Failed to decode message"), text);
+		b.bytes = text.len() as u64;
+		b.iter(|| tree.decode(&bits));
+	}
+
+	// Decode a string by walking the tree bit by bit
+	#[bench]
+	fn decode_tree_walk(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let bits = tree.encode(&text)
+			.expect("Failed to encode message");
+
+		assert_eq!(tree.decode_iter(&bits).collect::<anyhow::Result<String>>().expect("Failed to decode message"), text);
+		b.bytes = text.len() as u64;
+		b.iter(|| tree.decode_iter(&bits).collect::<anyhow::Result<String>>());
+	}
+
+	// Decode a character with the cached decoder
+	#[bench]
+	fn decode_character(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let bits = tree.encode("c")
+			.expect("Failed to encode message");
+
+		assert_eq!(tree.decode(&bits).expect("Failed to decode message"), "c");
+		b.iter(|| tree.decode(black_box(&bits)));
+	}
+
+	// Decode a character by walking the tree
+	#[bench]
+	fn walk_symbol(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let bits = tree.encode("c")
+			.expect("Failed to encode message");
+
+		assert_eq!(tree.walk_symbol(&bits, 0).expect("Failed to decode message"), ('c', bits.len()));
+		b.iter(|| tree.walk_symbol(black_box(&bits), 0));
+	}
+
+	// Encode a string straight into packed bytes
+	#[bench]
+	fn encode_packed(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		let (bytes, num_bits) = tree.encode_packed(&text).expect("Failed to encode message");
+		let bits = tree.encode(&text).expect("Failed to encode message");
+		assert_eq!(num_bits, bits.len());
+		assert_eq!(bytes, bits.to_bytes());
+
+		b.bytes = text.len() as u64;
+		b.iter(|| tree.encode_packed(&text));
+	}
+
+	// Pack codes too long for the packed code table
+	#[bench]
+	fn encode_packed_long_codes(b: &mut Bencher) {
+		let symbols: Vec<char> = ('a'..='z').chain('A'..='J').collect();
+		let mut lengths: Vec<(char, usize)> = symbols.iter()
+			.enumerate()
+			.map(|(index, symbol)| (*symbol, index + 1))
+			.collect();
+		lengths.last_mut().unwrap().1 -= 1;
+
+		let tree = super::HuffmanTree::from_code_lengths(&lengths)
+			.expect("Failed to build Huffman tree");
+		assert!(tree.depth() > super::super::packed_code::MAX_PACKED_BITS);
+		assert!(tree.packed_table.is_none());
+
+		let text: String = symbols.iter().collect();
+		let (bytes, num_bits) = tree.encode_packed(&text).expect("Failed to encode message");
+		let bits = tree.encode(&text).expect("Failed to encode message");
+		assert_eq!(num_bits, bits.len());
+		assert_eq!(bytes, bits.to_bytes());
+
+		b.iter(|| tree.encode_packed(&text));
+	}
+
+	// Encode a string to bits, then pack them into bytes
+	#[bench]
+	fn encode_then_to_bytes(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		b.bytes = text.len() as u64;
+		b.iter(|| tree.encode(&text).map(|bits| bits.to_bytes()));
+	}
+
+	// Encode a character
+	#[bench]
+	fn encode_character(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		b.iter(|| tree.encode_character(black_box(&'c')));
+	}
+
+	// Look up characters that have no code without building errors
+	#[bench]
+	fn try_code_miss(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let misses: Vec<char> = ('😀'..='🙏').collect();
+
+		assert_eq!(tree.try_code('c'), Some(tree.encode_character(&'c').expect("Failed to encode c")));
+		assert!(misses.iter().all(|character| tree.try_code(*character).is_none()));
+
+		b.iter(|| black_box(&misses).iter().filter(|character| tree.try_code(**character).is_none()).count());
+	}
+
+	// Look up characters that have no code through the error path
+	#[bench]
+	fn encode_character_miss(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let misses: Vec<char> = ('😀'..='🙏').collect();
+
+		b.iter(|| black_box(&misses).iter().filter(|character| tree.encode_character(character).is_err()).count());
+	}
+
+	// Get a character's code packed into an integer
+	#[bench]
+	fn code_packed(b: &mut Bencher) {
+		let tree = super::HuffmanTree::from_code_lengths(&[('a', 1), ('b', 2), ('c', 3), ('d', 3)])
+			.expect("Failed to build Huffman tree");
+
+		assert_eq!(tree.code_packed('a'), Some((0b0, 1)));
+		assert_eq!(tree.code_packed('c'), Some((0b110, 3)));
+		assert_eq!(tree.code_packed('d'), Some((0b111, 3)));
+		assert_eq!(tree.code_packed('e'), None);
+
+		b.iter(|| tree.code_packed(black_box('c')));
+	}
+
+	// Get letter frequencies
+	#[bench]
+	fn get_letter_frequencies(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+
+		b.iter(|| super::get_letter_frequencies(&text));
+	}
+
+	// Lookup bits from table
+	#[bench]
+	fn tree_lookup(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		b.iter(|| {
+			let _ = tree.lookup_table.get(black_box('Q'));
+		})
+	}
+
+	// Check the codebook is prefix-free
+	#[bench]
+	fn is_prefix_free(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let codes: Vec<_> = tree.lookup_table.values().cloned().collect();
+
+		assert!(super::is_prefix_free(&codes));
+
+		let long: super::Bits = "0".repeat(1_000_000).parse().expect("Failed to parse bits");
+		let short: super::Bits = "1".parse().expect("Failed to parse bits");
+		let prefix: super::Bits = "000".parse().expect("Failed to parse bits");
+		assert!(super::is_prefix_free(&[long.clone(), short]));
+		assert!(!super::is_prefix_free(&[long, prefix]));
+
+		b.iter(|| super::is_prefix_free(black_box(&codes)));
+	}
+
+	// Sum the Kraft inequality over the codebook
+	#[bench]
+	fn kraft_sum(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		assert_eq!(tree.kraft_sum(), 1.0);
+		b.iter(|| tree.kraft_sum());
+	}
+
+	// Check that every symbol has its own code
+	#[bench]
+	fn has_unique_codes(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		assert!(tree.has_unique_codes());
+		assert!(super::HuffmanTree::new("a").expect("Failed to build Huffman tree").has_unique_codes());
+		assert!(super::HuffmanTree::new_with_eof(&text).expect("Failed to build Huffman tree").has_unique_codes());
+		assert!(super::HuffmanTree::new_with_rare_threshold(&text, 10).expect("Failed to build Huffman tree").has_unique_codes());
+		assert!(super::HuffmanTree::from_code_lengths(&tree.code_lengths()).expect("Failed to build Huffman tree").has_unique_codes());
+		assert!(HuffmanTree::english().has_unique_codes());
+		assert!(super::HuffmanTree::fixed_deflate().has_unique_codes());
+
+		b.iter(|| tree.has_unique_codes());
+	}
+
+	// Measure how full the lookup table is
+	#[bench]
+	fn lookup_table_density(b: &mut Bencher) {
+		let tree = super::HuffmanTree::new("ab😀")
+			.expect("Failed to build Huffman tree");
 
-		while table.len() > 1 {
-			Self::build_tree(&mut table)?;
+		assert_eq!(tree.lookup_table_density(), 3.0 / ('😀' as usize + 1) as f64);
+		b.iter(|| tree.lookup_table_density());
+	}
+
+	// Build a tree from extremely skewed frequencies
+	#[bench]
+	fn skewed(b: &mut Bencher) {
+		let mut counts = vec![1usize, 1];
+		while counts.len() < 80 {
+			counts.push(counts[counts.len() - 1] + counts[counts.len() - 2]);
 		}
+		let frequencies: super::Frequencies = counts.iter()
+			.enumerate()
+			.map(|(index, count)| (char::from_u32(0x100 + index as u32).unwrap(), *count))
+			.collect();
+
+		let tree = super::HuffmanTree::from_frequencies(frequencies.clone())
+			.expect("Failed to build Huffman tree");
+		assert_eq!(tree.depth(), counts.len() - 1);
+		assert!(tree.check_depth(counts.len() - 1).is_ok());
+		assert!(tree.check_depth(32).is_err());
 
-		let tree = Rc::clone(&table[0].1);
-		let mut lookup_table = FnvHashMap::default();
-		Self::traverse(&tree, &mut lookup_table, &Bits::new());
+		let text: String = frequencies.keys().collect();
+		let bits = tree.encode(&text).expect("Failed to encode message");
+		assert_eq!(tree.decode(&bits).expect("Failed to decode message"), text);
 
-		let lookup_table = Self::convert_table(lookup_table);
-		Ok(HuffmanTree{lookup_table})
+		b.iter(|| super::HuffmanTree::from_frequencies(frequencies.clone()));
 	}
 
-	/// Runs one step of building the tree
-	///
-	/// This will error when there are less than two nodes to combine.
-	fn build_tree(table: &mut NodeTable) -> Result<()> {
-		if table.len() < 2 {
-			return Err(anyhow!("Attempt to build tree without two nodes to combine"));
-		}
+	// Round trip NUL, the replacement character and the largest codepoint
+	#[bench]
+	fn boundary_codepoints(b: &mut Bencher) {
+		let text = "\0a\0\u{FFFD}b\u{10FFFF}\0";
+		let tree = super::HuffmanTree::new(text)
+			.expect("Failed to build Huffman tree");
+		assert!(matches!(tree.lookup_table, super::LookupTable::Remapped {..}));
+		assert!(tree.self_check(text).is_ok());
+		assert_eq!(tree.lookup_table_density(), 5.0 / (char::MAX as usize + 1) as f64);
 
-		let (n1, left) = table.remove(0);
-		let (n2, right) = table.remove(0);
+		let lone = super::HuffmanTree::new("\u{10FFFF}")
+			.expect("Failed to build Huffman tree");
+		assert!(matches!(lone.lookup_table, super::LookupTable::Remapped {ref values, ..} if values.len() == 1));
+		assert!(lone.self_check("\u{10FFFF}\u{10FFFF}").is_ok());
 
-		let num = n1 + n2;
-		let node = Rc::new(Node::new_parent(left, right));
+		let nul = super::HuffmanTree::new("\0\0")
+			.expect("Failed to build Huffman tree");
+		assert!(matches!(nul.lookup_table, super::LookupTable::Dense(ref slots) if slots.len() == 1));
+		assert!(nul.self_check("\0").is_ok());
 
-		Self::insert_table_entry(table, num, node);
-		Ok(())
+		let container = crate::compress(text).expect("Failed to compress message");
+		assert_eq!(crate::decompress(&container, true).expect("Failed to decompress message"), text);
+
+		let mut encoder = super::super::WindowedEncoder::new(2).expect("Failed to build encoder");
+		encoder.encode(text).expect("Failed to encode message");
+		let bits = encoder.finish().expect("Failed to finish encoding");
+		assert_eq!(super::super::decode_windowed(&bits).expect("Failed to decode message"), text);
+
+		b.iter(|| super::HuffmanTree::new(black_box(text)));
 	}
 
-	/// Converts the hashmap table to a Vec
-	///
-	/// Since there are only a few hundred options, this sacrifices a bit of
-	/// space to avoid running the hash function.
-	fn convert_table(table: HashMap<char, Bits, BuildHasherDefault<FnvHasher>>) -> Vec<Option<Bits>> {
-		let (max_char, _) = table.iter()
-			.max_by_key(|(c, _)| u32::from(**c))
-			.unwrap();
+	// Collect the codes of a tree as deep as its alphabet
+	#[bench]
+	fn traverse_deep(b: &mut Bencher) {
+		let mut lengths: Vec<(char, usize)> = (0..2000)
+			.map(|index| (char::from_u32(0x4E00 + index).unwrap(), index as usize + 1))
+			.collect();
+		lengths.last_mut().unwrap().1 -= 1;
 
-		// Initialize table
-		let mut output_table = Vec::new();
-		for _ in 0..u32::from(*max_char) + 1 {
-			output_table.push(None);
-		}
+		let tree = super::HuffmanTree::from_code_lengths(&lengths)
+			.expect("Failed to build Huffman tree");
+		assert_eq!(tree.depth(), 1999);
+		assert_eq!(tree.kraft_sum(), 1.0);
+
+		b.iter(|| super::HuffmanTree::code_map(&tree.root, super::BitConvention::default()));
+	}
+
+	// Check a tree decodes its own encoding
+	#[bench]
+	fn self_check(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::from_code_lengths(&super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree")
+			.code_lengths())
+			.expect("Failed to build Huffman tree");
+
+		assert!(tree.self_check(&text).is_ok());
+		assert!(tree.self_check("😀").is_err());
+
+		b.iter(|| tree.self_check(&text));
+	}
+
+	// Construct the fixed DEFLATE code
+	#[bench]
+	fn fixed_deflate(b: &mut Bencher) {
+		let tree = super::HuffmanTree::fixed_deflate();
+		let code = |symbol: u32| tree.encode_character(&char::from_u32(symbol).unwrap()).unwrap().to_string();
+
+		assert_eq!(code(0), "00110000");
+		assert_eq!(code(143), "10111111");
+		assert_eq!(code(144), "110010000");
+		assert_eq!(code(255), "111111111");
+		assert_eq!(code(256), "0000000");
+		assert_eq!(code(279), "0010111");
+		assert_eq!(code(280), "11000000");
+		assert_eq!(code(287), "11000111");
+		assert_eq!(tree.alphabet_size(), 288);
+
+		b.iter(super::HuffmanTree::fixed_deflate);
+	}
+
+	// Construct a tree from a literal table of counts
+	#[bench]
+	fn from_pairs(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let frequencies = super::get_letter_frequencies(&text);
+		let mut pairs: Vec<(char, usize)> = frequencies.clone().into_iter().collect();
+		pairs.sort_unstable_by_key(|(character, count)| (std::cmp::Reverse(*count), *character));
+
+		let tree = HuffmanTree::from_pairs(&pairs)
+			.expect("Failed to build Huffman tree");
+		let expected = HuffmanTree::from_frequencies(frequencies)
+			.expect("Failed to build Huffman tree");
+		assert_eq!(tree.code_lengths(), expected.code_lengths());
+		assert_eq!(tree.encode(&text).expect("Failed to encode message"), expected.encode(&text).expect("Failed to encode message"));
 
-		table.iter().for_each(|(character, bits)| {
-			let index = *character as usize;
-			output_table[index] = Some(bits.clone());
+		assert!(HuffmanTree::from_pairs(&[]).is_err());
+		assert!(HuffmanTree::from_pairs(&[('a', 1), ('b', 2), ('a', 3)]).is_err());
+
+		b.iter(|| HuffmanTree::from_pairs(black_box(&pairs)));
+	}
+
+	// Build one tree from counts taken on separate threads
+	#[bench]
+	fn combine_training(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let (first, second) = text.split_at(text.len() / 2);
+
+		let (a, b_counter) = std::thread::scope(|scope| {
+			let count = |shard| scope.spawn(move || {
+				let mut counter = super::FrequencyCounter::new();
+				counter.add_text(shard);
+				counter
+			});
+			let (a, b_counter) = (count(first), count(second));
+			(a.join().expect("Failed to count shard"), b_counter.join().expect("Failed to count shard"))
 		});
 
-		output_table
+		let tree = HuffmanTree::combine_training(&a, &b_counter)
+			.expect("Failed to build Huffman tree");
+		let expected = HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		assert_eq!(tree.code_lengths(), expected.code_lengths());
+		assert!(HuffmanTree::combine_training(&super::FrequencyCounter::new(), &super::FrequencyCounter::new()).is_err());
+
+		b.iter(|| HuffmanTree::combine_training(black_box(&a), &b_counter));
 	}
 
-	/// Encodes a string to a Bits object using this Huffman tree
-	///
-	/// This uses the lookup table learned from the example text provided at
-	/// construction. This function will fail if any characters being encoded
-	/// were not in that initial text.
-	pub fn encode(&self, string: &String) -> Result<Bits> {
-		let mut encoded_string = Bits::new();
+	// List codes in canonical order
+	#[bench]
+	fn canonical_order(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
 
-		for character in string.chars() {
-			let encoded_character = self.encode_character(&character)?;
-			encoded_string.append(encoded_character);
-		}
+		let order = tree.canonical_order();
+		assert_eq!(order.len(), tree.alphabet_size());
+		assert!(order.windows(2).all(|pair| (pair[0].1.len(), pair[0].0) < (pair[1].1.len(), pair[1].0)));
+		assert!(order.iter().all(|(character, code)| tree.try_code(*character) == Some(code)));
 
-		Ok(encoded_string)
+		let canonical = HuffmanTree::from_code_lengths(&tree.code_lengths())
+			.expect("Failed to build canonical tree");
+		let lengths: Vec<_> = order.iter().map(|(character, code)| (*character, code.len())).collect();
+		assert_eq!(canonical.canonical_order(), super::canonical_codes(&lengths).expect("Failed to assign canonical codes"));
+
+		b.iter(|| black_box(&tree).canonical_order());
 	}
 
-	/// Encodes a single character to a Bits object using this Huffman tree
-	///
-	/// This uses the lookup table learned from the example text provided at
-	/// construction. This function will fail if the character being encoded was
-	/// not in that initial text.
-	fn encode_character(&self, character: &char) -> Result<&Bits> {
-		let error_message = "Character not found in lookup table";
+	// Encode with each code starting on a byte boundary
+	#[bench]
+	fn encode_byte_aligned(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
 
-		let index = *character as usize;
-		let result = self.lookup_table.get(index);
+		let bytes = tree.encode_byte_aligned(&text).expect("Failed to encode message");
+		let expected_bytes: usize = text.chars()
+			.map(|character| tree.encode_character(&character).expect("Failed to encode character").len().div_ceil(8))
+			.sum();
+		assert_eq!(bytes.len(), expected_bytes);
+		assert_eq!(tree.decode_byte_aligned(&bytes).expect("Failed to decode message"), text);
 
-		match result {
-			Some(res) => res.as_ref().context(error_message),
-			None => Err(anyhow!(error_message))
+		let message = "a bad cab";
+		let small = super::HuffmanTree::new(message)
+			.expect("Failed to build Huffman tree");
+		let bytes = small.encode_byte_aligned(message).expect("Failed to encode message");
+		assert_eq!(bytes.len(), message.len());
+		for (index, character) in message.chars().enumerate() {
+			assert_eq!(small.decode_byte_aligned(&bytes[index..=index]).expect("Failed to decode symbol"), character.to_string());
 		}
+
+		let eof_tree = super::HuffmanTree::new_with_eof(message)
+			.expect("Failed to build Huffman tree");
+		let mut bytes = eof_tree.encode_byte_aligned(message).expect("Failed to encode message");
+		bytes.push(0xFF);
+		assert_eq!(eof_tree.decode_byte_aligned(&bytes).expect("Failed to decode message"), message);
+
+		let rare_tree = super::HuffmanTree::new_with_rare_threshold(message, 2)
+			.expect("Failed to build Huffman tree");
+		let bytes = rare_tree.encode_byte_aligned("a bad cab ☃").expect("Failed to encode message");
+		assert_eq!(rare_tree.decode_byte_aligned(&bytes).expect("Failed to decode message"), "a bad cab ☃");
+
+		b.iter(|| tree.encode_byte_aligned(black_box(&text)));
 	}
 
-	/// Traverse a subtree and extract its data into a lookup table
-	fn traverse(subtree: &Rc<Node>, table: &mut HashMap<char, Bits, BuildHasherDefault<FnvHasher>>, code: &Bits) {
-		if let Some(value) = subtree.value {
-			table.insert(value, code.clone());
-		} else {
-			Self::traverse_child(&subtree.left_child, table, code, false);
-			Self::traverse_child(&subtree.right_child, table, code, true);
+	// List the branches of the tree
+	#[bench]
+	fn edges(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let edges = tree.edges();
+
+		assert_eq!(edges.len() + 1, tree.to_ascii_tree().lines().count());
+		assert!(edges.iter().enumerate().all(|(index, (parent, child, _))| *child == index + 1 && parent < child));
+
+		// Follow the edges down to each leaf to rebuild the codes
+		let mut paths = vec![String::new()];
+		for (parent, _, bit) in &edges {
+			paths.push(format!("{}{}", paths[*parent], u8::from(*bit)));
 		}
+		let mut leaf_codes: Vec<String> = (0..paths.len())
+			.filter(|node| edges.iter().all(|(parent, _, _)| parent != node))
+			.map(|node| paths[node].clone())
+			.collect();
+		let mut codes: Vec<String> = tree.canonical_order().iter().map(|(_, code)| code.to_string()).collect();
+		leaf_codes.sort_unstable();
+		codes.sort_unstable();
+		assert_eq!(leaf_codes, codes);
+
+		let small = super::HuffmanTree::from_code_lengths(&[('a', 1), ('b', 2), ('c', 2)])
+			.expect("Failed to build Huffman tree");
+		assert_eq!(small.edges(), [(0, 1, false), (0, 2, true), (2, 3, false), (2, 4, true)]);
+		assert!(super::HuffmanTree::new("aaa").expect("Failed to build Huffman tree").edges().is_empty());
+
+		b.iter(|| tree.edges());
+	}
+
+	// Fold case together before counting and encoding
+	#[bench]
+	fn new_normalized(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new_normalized(&text, |c| c.to_ascii_lowercase())
+			.expect("Failed to build Huffman tree");
+		let lowercase = text.to_ascii_lowercase();
+
+		assert_eq!(tree.code_lengths(), super::HuffmanTree::new(&lowercase).expect("Failed to build Huffman tree").code_lengths());
+		assert!(tree.try_code('A').is_none());
+
+		let bits = tree.encode(&text).expect("Failed to encode message");
+		assert_eq!(bits, tree.encode(&lowercase).expect("Failed to encode message"));
+		assert_eq!(tree.decode(&bits).expect("Failed to decode message"), lowercase);
+		assert_eq!(tree.encode_packed(&text).expect("Failed to encode message"), (bits.to_bytes(), bits.len()));
+		assert_eq!(tree.total_encoded_bits(&super::get_letter_frequencies(&text)).expect("Failed to count bits"), bits.len());
+
+		let mut retrained = tree.clone();
+		retrained.retrain("AAAb").expect("Failed to retrain tree");
+		assert_eq!(retrained.decode(&retrained.encode("Ab").expect("Failed to encode message")).expect("Failed to decode message"), "ab");
+
+		b.bytes = text.len() as u64;
+		b.iter(|| tree.encode(black_box(&text)));
 	}
 
-	/// Traverses down a child node in a subtree
-	fn traverse_child(child: &Option<Rc<Node>>, table: &mut HashMap<char, Bits, BuildHasherDefault<FnvHasher>>, code: &Bits, new_bit: bool) {
-		if let Some(node) = child {
-			let mut code = code.clone();
-			code.add(new_bit);
+	// Encode and decode many short messages with one tree
+	#[bench]
+	fn encode_batch(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let messages: Vec<&str> = text.lines().collect();
 
-			Self::traverse(&node, table, &code);
+		let batch = tree.encode_batch(&messages).expect("Failed to encode messages");
+		assert_eq!(batch.len(), messages.len());
+		for (message, bits) in messages.iter().zip(&batch) {
+			assert_eq!(*bits, tree.encode(message).expect("Failed to encode message"));
 		}
+		assert_eq!(tree.decode_batch(&batch).expect("Failed to decode messages"), messages);
+
+		let eof_tree = super::HuffmanTree::new_with_eof("abc").expect("Failed to build Huffman tree");
+		let eof_batch = eof_tree.encode_batch(&["ab", "", "c"]).expect("Failed to encode messages");
+		assert_eq!(eof_tree.decode_batch(&eof_batch).expect("Failed to decode messages"), ["ab", "", "c"]);
+
+		let error = eof_tree.encode_batch(&["a", "b", "z"]).expect_err("Encoded a missing symbol");
+		assert_eq!(error.to_string(), "Failed to encode message 2");
+		assert!(tree.encode_batch(&[]).expect("Failed to encode messages").is_empty());
+
+		b.bytes = text.len() as u64;
+		b.iter(|| tree.encode_batch(black_box(&messages)));
 	}
 
+	// Weigh the bits encoding saves against the size of the tree
+	#[bench]
+	fn net_savings(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
 
-	/// Initializes a sorted table of leaf nodes from a hash map of character frequencies
-	fn init_table(frequencies: HashMap<char, usize, BuildHasherDefault<FnvHasher>>) -> NodeTable {
-		let mut table: Vec<(char, usize)> = frequencies.into_iter().collect();
-		table.sort_unstable_by_key(|e| e.1);
+		let container = crate::compress(&text).expect("Failed to compress message");
+		let encoded_bits = tree.encode(&text).expect("Failed to encode message").len();
+		assert_eq!(container.len() * 8, 4 * 8 + 32 + tree.serialized_bits() + 8 + encoded_bits.next_multiple_of(8));
 
-		table.iter()
-			.map(|(character, num)| {
-				let node = Node::new_leaf(*character);
-				(*num, Rc::new(node))
-			})
-			.collect()
+		let savings = tree.net_savings(&text).expect("Failed to measure savings");
+		assert_eq!(savings, (text.chars().count() * 8) as i64 - (encoded_bits + tree.serialized_bits()) as i64);
+		assert!(savings > 0);
+
+		let short = super::HuffmanTree::new("abcdef").expect("Failed to build Huffman tree");
+		assert!(short.net_savings("abcdef").expect("Failed to measure savings") < 0);
+		assert!(short.net_savings("xyz").is_err());
+
+		b.bytes = text.len() as u64;
+		b.iter(|| tree.net_savings(black_box(&text)));
 	}
 
-	/// Finds a spot to insert an entry into a node table
-	fn insert_table_entry(table: &mut NodeTable, num: usize, node: Rc<Node>) {
-		let position = table.binary_search_by_key(&num, |(n, _)| *n);
+	// Decode with a cap on the number of symbols
+	#[bench]
+	fn decode_limited(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let bits = tree.encode(&text).expect("Failed to encode message");
+		let symbols = text.chars().count();
 
-		match position {
-			Ok(pos) => table.insert(pos, (num, node)),
-			Err(pos) => table.insert(pos, (num, node)),
-		}
+		assert_eq!(tree.decode_limited(&bits, symbols).expect("Failed to decode message"), text);
+		let error = tree.decode_limited(&bits, symbols - 1).expect_err("Decoded past the limit");
+		assert_eq!(error.to_string(), format!("Decoded output exceeds {} symbols", symbols - 1));
+
+		let small = super::HuffmanTree::from_code_lengths(&[('a', 1), ('b', 2), ('c', 2)])
+			.expect("Failed to build Huffman tree");
+		let mut truncated = small.encode("ab").expect("Failed to encode message");
+		truncated.add(true);
+		let limited = small.decode_limited(&truncated, 2).expect_err("Decoded a truncated code");
+		let unlimited = small.decode(&truncated).expect_err("Decoded a truncated code");
+		assert_eq!(limited.to_string(), unlimited.to_string());
+
+		b.bytes = text.len() as u64;
+		b.iter(|| tree.decode_limited(black_box(&bits), symbols));
 	}
-}
 
-/// Generates a list of letter frequencies
-///
-/// Returns a map of characters and the number of times they appear
-fn get_letter_frequencies(string: &String) -> HashMap<char, usize, BuildHasherDefault<FnvHasher>> {
-	let mut frequencies = FnvHashMap::with_capacity_and_hasher(100, Default::default());
+	// Decode the complete symbols of a truncated message
+	#[bench]
+	fn decode_prefix(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let bits = tree.encode(&text).expect("Failed to encode message");
+		assert_eq!(tree.decode_prefix(&bits), (text.clone(), 0));
 
-	for character in string.chars() {
-		let entry = frequencies.get_mut(&character);
+		let small = super::HuffmanTree::from_code_lengths(&[('a', 1), ('b', 2), ('c', 2)])
+			.expect("Failed to build Huffman tree");
+		let encoded = small.encode("abc").expect("Failed to encode message");
+		let truncated = super::Bits::from_bool_slice(&encoded.as_slice()[..encoded.len() - 1]);
+		assert_eq!(small.decode_prefix(&truncated), ("ab".to_string(), 1));
+		assert_eq!(small.decode_prefix(&super::Bits::new()), (String::new(), 0));
 
-		match entry {
-			Some(e) => *e += 1,
-			None => {frequencies.insert(character, 1);}
-		};
+		let eof_tree = super::HuffmanTree::new_with_eof("aab")
+			.expect("Failed to build Huffman tree");
+		let mut terminated = eof_tree.encode("ab").expect("Failed to encode message");
+		let length = terminated.len();
+		terminated.add(false);
+		terminated.add(true);
+		assert_eq!(eof_tree.decode_prefix(&terminated), ("ab".to_string(), 2));
+		assert_eq!(eof_tree.decode_prefix(&super::Bits::from_bool_slice(&terminated.as_slice()[..length - 1])).0, "ab");
+
+		let escape_tree = super::HuffmanTree::new_with_rare_threshold("aaaab", 2)
+			.expect("Failed to build Huffman tree");
+		let escaped = escape_tree.encode("aé").expect("Failed to encode message");
+		let cut = super::Bits::from_bool_slice(&escaped.as_slice()[..escaped.len() - 3]);
+		let (prefix, leftover) = escape_tree.decode_prefix(&cut);
+		assert_eq!(prefix, "a");
+		assert_eq!(cut.len() - leftover, escape_tree.encode("a").expect("Failed to encode message").len());
+
+		b.bytes = text.len() as u64;
+		b.iter(|| tree.decode_prefix(black_box(&bits)));
 	}
 
-	frequencies
-}
+	// Clone a trained tree and print a summary of it
+	#[bench]
+	fn clone(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let bits = tree.encode(&text).expect("Failed to encode message");
+		tree.decode(&bits).expect("Failed to decode message");
 
-mod benchmarks {
+		let copy = tree.clone();
+		assert!(std::sync::Arc::ptr_eq(&copy.root, &tree.root));
+		assert!(copy.decoder.get().is_some());
+		assert_eq!(copy.encode(&text).expect("Failed to encode message"), bits);
+		assert_eq!(copy.decode(&bits).expect("Failed to decode message"), text);
 
-	#[allow(unused)]
-	use test::Bencher;
-	#[allow(unused)]
-	use test::black_box;
-	#[allow(unused)]
-	use super::HuffmanTree;
+		let mut retrained = tree.clone();
+		retrained.retrain("abc").expect("Failed to retrain tree");
+		assert_eq!(tree.encode(&text).expect("Failed to encode message"), bits);
 
-	// Construct a new tree
+		let small = super::HuffmanTree::from_code_lengths(&[('a', 1), ('b', 1)])
+			.expect("Failed to build Huffman tree");
+		assert_eq!(format!("{small:?}"), "HuffmanTree { alphabet_size: 2, depth: 1, codes: [('a', 0), ('b', 1)] }");
+
+		b.iter(|| black_box(&tree).clone());
+	}
+
+	// Draw a tree as indented text
 	#[bench]
-	fn new(b: &mut Bencher) {
+	fn to_ascii_tree(b: &mut Bencher) {
+		let tree = HuffmanTree::from_code_lengths(&[('a', 1), ('b', 2), ('\n', 3), (' ', 3)])
+			.expect("Failed to build Huffman tree");
+		assert_eq!(tree.to_ascii_tree(), concat!(
+			".\n",
+			"├── 0 'a'\n",
+			"└── 1\n",
+			"    ├── 10 'b'\n",
+			"    └── 11\n",
+			"        ├── 110 '\\n'\n",
+			"        └── 111 ' '\n",
+		));
+		assert_eq!(HuffmanTree::new("aaa").expect("Failed to build Huffman tree").to_ascii_tree(), "'a'\n");
+
 		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
 			.expect("Failed to read file");
+		let tree = HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		let drawing = tree.to_ascii_tree();
+		assert_eq!(drawing.lines().count(), 2 * tree.alphabet_size() - 1);
+		assert!(drawing.lines().filter(|line| line.ends_with('\'')).count() == tree.alphabet_size());
 
-		b.iter(|| HuffmanTree::new(&text));
+		b.iter(|| black_box(&tree).to_ascii_tree());
 	}
 
-	// Encode a string
+	// Round trip the empty string with and without an end of stream symbol
 	#[bench]
-	fn encode(b: &mut Bencher) {
+	fn encode_empty(b: &mut Bencher) {
 		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
 			.expect("Failed to read file");
 		let tree = super::HuffmanTree::new(&text)
 			.expect("Failed to build Huffman tree");
+		let eof_tree = super::HuffmanTree::new_with_eof(&text)
+			.expect("Failed to build Huffman tree");
+		let rare_tree = super::HuffmanTree::new_with_rare_threshold(&text, 20)
+			.expect("Failed to build Huffman tree");
 
-		b.iter(|| tree.encode(&text));
+		let empty = tree.encode("").expect("Failed to encode message");
+		assert_eq!(empty.len(), 0);
+		assert_eq!(tree.encode_packed("").expect("Failed to encode message"), (Vec::new(), 0));
+		assert_eq!(rare_tree.encode("").expect("Failed to encode message").len(), 0);
+
+		let eof_code = eof_tree.encode_character(&super::EOF_SYMBOL).expect("Failed to find end of stream code");
+		let eof_only = eof_tree.encode("").expect("Failed to encode message");
+		assert_eq!(&eof_only, eof_code);
+		let padded = super::Bits::from_bytes(&eof_only.to_bytes(), eof_only.to_bytes().len() * 8)
+			.expect("Failed to read padded bits");
+
+		for (tree, bits) in [(&tree, &empty), (&rare_tree, &empty), (&eof_tree, &eof_only), (&eof_tree, &padded)] {
+			assert_eq!(tree.decode(bits).expect("Failed to decode message"), "");
+			assert_eq!(tree.decode_iter(bits).count(), 0);
+
+			let mut state = tree.decode_state();
+			assert!(bits.iter().all(|bit| state.feed(bit).expect("Failed to decode bit").is_none()));
+		}
+
+		b.iter(|| eof_tree.encode(black_box("")));
 	}
 
-	// Encode a character
+	// Compute each symbol's share of the encoded output
 	#[bench]
-	fn encode_character(b: &mut Bencher) {
+	fn symbol_cost(b: &mut Bencher) {
 		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
 			.expect("Failed to read file");
 		let tree = super::HuffmanTree::new(&text)
 			.expect("Failed to build Huffman tree");
+		let frequencies = super::get_letter_frequencies(&text);
 
-		b.iter(|| tree.encode_character(black_box(&'c')));
+		let costs = tree.symbol_cost(&frequencies).expect("Failed to compute costs");
+		assert_eq!(costs.len(), tree.alphabet_size());
+		assert!(costs.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+		assert_eq!(costs.iter().map(|(_, cost)| cost).sum::<usize>(), tree.encode(&text).expect("Failed to encode message").len());
+		assert_eq!(costs[0].0, ' ');
+
+		let mut missing = frequencies.clone();
+		missing.insert('😀', 1);
+		assert!(tree.symbol_cost(&missing).is_err());
+		missing.insert('😀', 0);
+		assert_eq!(tree.symbol_cost(&missing).expect("Failed to compute costs"), costs);
+
+		b.iter(|| tree.symbol_cost(black_box(&frequencies)));
 	}
 
-	// Get letter frequencies
+	// Encode emoji and astral CJK without a codepoint-sized table
 	#[bench]
-	fn get_letter_frequencies(b: &mut Bencher) {
+	fn astral(b: &mut Bencher) {
+		let text = "😀 𠀀𠀁𠀂 𪚥𪚥 🙏🙏🙏 汉字 𝄞 hello";
+		let tree = super::HuffmanTree::new(text)
+			.expect("Failed to build Huffman tree");
+		assert!(!matches!(tree.lookup_table, super::LookupTable::Dense(_)));
+		assert!(tree.lookup_table_density() < 0.001);
+
+		let bits = tree.encode(text).expect("Failed to encode message");
+		assert_eq!(tree.decode(&bits).expect("Failed to decode message"), text);
+		assert_eq!(tree.encode_packed(text).expect("Failed to encode message"), (bits.to_bytes(), bits.len()));
+
+		for strategy in [super::LookupStrategy::DenseVec, super::LookupStrategy::HashMap] {
+			let other = super::HuffmanTree::new_with_lookup_strategy(text, strategy)
+				.expect("Failed to build Huffman tree");
+			assert_eq!(other.encode(text).expect("Failed to encode message"), bits);
+		}
+
+		b.iter(|| tree.encode(black_box(text)));
+	}
+
+	// Load a tree from a cache file, rebuilding it when stale
+	#[bench]
+	fn load_or_build(b: &mut Bencher) {
 		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
 			.expect("Failed to read file");
+		let path = std::env::temp_dir().join(format!("huffman-tree-cache-{}.txt", std::process::id()));
+		let _ = std::fs::remove_file(&path);
 
-		b.iter(|| super::get_letter_frequencies(&text));
+		let built = HuffmanTree::load_or_build(&path, &text)
+			.expect("Failed to build Huffman tree");
+		let contents = std::fs::read_to_string(&path).expect("Failed to read cache");
+		assert!(contents.starts_with(&super::cache_header(&text)));
+
+		let loaded = HuffmanTree::load_or_build(&path, &text)
+			.expect("Failed to load Huffman tree");
+		assert_eq!(loaded.to_text(), built.to_text());
+
+		let stale = HuffmanTree::load_or_build(&path, "abc")
+			.expect("Failed to rebuild Huffman tree");
+		assert_eq!(stale.alphabet_size(), 3);
+
+		std::fs::write(&path, "not a cache").expect("Failed to write cache");
+		let rebuilt = HuffmanTree::load_or_build(&path, &text)
+			.expect("Failed to rebuild Huffman tree");
+		assert_eq!(rebuilt.to_text(), built.to_text());
+		assert!(HuffmanTree::load_or_build(std::env::temp_dir(), &text).is_err());
+
+		b.iter(|| HuffmanTree::load_or_build(&path, black_box(&text)));
+		std::fs::remove_file(&path).expect("Failed to remove cache");
 	}
 
-	// Lookup bits from table
+	// Rebuild a canonical tree from code lengths
 	#[bench]
-	fn tree_lookup(b: &mut Bencher) {
+	fn from_code_lengths(b: &mut Bencher) {
 		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
 			.expect("Failed to read file");
 		let tree = super::HuffmanTree::new(&text)
 			.expect("Failed to build Huffman tree");
+		let lengths = tree.code_lengths();
 
-		b.iter(|| {
-			let index = 'Q' as usize;
-			let _ = tree.lookup_table.get(black_box(index));
-		})
+		let canonical = HuffmanTree::from_code_lengths(&lengths)
+			.expect("Failed to build canonical tree");
+		let bits = canonical.encode(&text)
+			.expect("Failed to encode message");
+		assert_eq!(canonical.code_lengths(), lengths);
+		assert_eq!(canonical.decode(&bits).expect("Failed to decode message"), text);
+
+		b.iter(|| HuffmanTree::from_code_lengths(black_box(&lengths)));
+	}
+
+	// Decode padded bits from a tree with an end of stream symbol
+	#[bench]
+	fn new_with_eof(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let tree = super::HuffmanTree::new_with_eof(&text)
+			.expect("Failed to build Huffman tree");
+		let eof_code = tree.encode_character(&super::EOF_SYMBOL)
+			.expect("Failed to find end of stream code");
+
+		let bits = tree.encode(&text)
+			.expect("Failed to encode message");
+		let mut symbols_only = super::Bits::new();
+		HuffmanTree::encode_with_tables(&tree.lookup_table, &tree.byte_table, &text, &mut symbols_only)
+			.expect("Failed to encode message");
+		assert_eq!(bits.len(), symbols_only.len() + eof_code.len());
+		assert_eq!(bits.len(), tree.total_encoded_bits(&super::get_letter_frequencies(&text)).expect("Failed to count bits"));
+		assert!(bits.as_slice().ends_with(eof_code.as_slice()));
+
+		let bytes = bits.to_bytes();
+		let padded = super::Bits::from_bytes(&bytes, bytes.len() * 8)
+			.expect("Failed to read padded bits");
+		assert!(padded.len() > bits.len());
+		assert_eq!(tree.decode(&padded).expect("Failed to decode message"), text);
+		assert_eq!(tree.decode_iter(&padded).collect::<anyhow::Result<String>>().expect("Failed to decode message"), text);
+
+		let mut state = tree.decode_state();
+		let streamed: String = padded.iter()
+			.filter_map(|bit| state.feed(bit).expect("Failed to decode bit"))
+			.collect();
+		assert!(state.is_finished());
+		assert_eq!(streamed, text);
+
+		assert!(tree.encode("\u{FFFF}").is_err());
+		assert!(super::HuffmanTree::new_with_eof("a\u{FFFF}").is_err());
+
+		b.iter(|| tree.decode(black_box(&padded)));
+	}
+
+	// Round trip text through a tree that escapes rare symbols
+	#[bench]
+	fn new_with_rare_threshold(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file") + "é😀";
+		let tree = super::HuffmanTree::new_with_rare_threshold(&text, 20)
+			.expect("Failed to build Huffman tree");
+		let plain = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+		assert!(tree.alphabet_size() < plain.alphabet_size());
+		assert!(tree.depth() < plain.depth());
+		assert!(!tree.can_encode('😀'));
+
+		let bits = tree.encode(&text)
+			.expect("Failed to encode message");
+		assert_eq!(tree.decode(&bits).expect("Failed to decode message"), text);
+		assert_eq!(tree.decode_iter(&bits).collect::<anyhow::Result<String>>().expect("Failed to decode message"), text);
+		assert_eq!(tree.total_encoded_bits(&super::get_letter_frequencies(&text)).expect("Failed to count bits"), bits.len());
+		assert_eq!(tree.encode_packed(&text).expect("Failed to encode message"), (bits.to_bytes(), bits.len()));
+
+		let mut state = tree.decode_state();
+		let streamed: String = bits.iter()
+			.filter_map(|bit| state.feed(bit).expect("Failed to decode bit"))
+			.collect();
+		assert!(state.is_at_boundary());
+		assert_eq!(streamed, text);
+
+		let unseen = "naïve ☃";
+		let escape_len = tree.encode_character(&super::ESCAPE_SYMBOL).expect("Failed to find escape code").len();
+		let unseen_bits = tree.encode(unseen).expect("Failed to encode message");
+		assert_eq!(tree.decode(&unseen_bits).expect("Failed to decode message"), unseen);
+		assert_eq!(tree.encode("☃").expect("Failed to encode message").len(), escape_len + super::ESCAPE_CODEPOINT_BITS);
+
+		assert!(tree.encode("\u{FFFE}").is_err());
+		assert!(super::HuffmanTree::new_with_rare_threshold("a\u{FFFE}", 0).is_err());
+
+		b.iter(|| tree.encode(black_box(&text)));
 	}
 }
\ No newline at end of file
@@ -0,0 +1,55 @@
+use anyhow::{anyhow, Result};
+
+/// The first surrogate codepoint, which no `char` can hold
+const SURROGATE_START: u32 = 0xD800;
+/// The number of surrogate codepoints skipped over
+const SURROGATE_COUNT: u32 = 0x800;
+
+/// The largest ID that can be stored as a symbol
+pub const MAX_ID: u32 = char::MAX as u32 - SURROGATE_COUNT;
+
+/// Turns an integer ID into the symbol that stands for it
+///
+/// IDs below U+D800 are their own codepoints. Higher IDs are shifted past the
+/// surrogates, so every ID up to `MAX_ID` has exactly one symbol. This will
+/// error for larger IDs.
+pub fn id_to_symbol(id: u32) -> Result<char> {
+	let codepoint = match id {
+		0..SURROGATE_START => id,
+		_ => id.saturating_add(SURROGATE_COUNT),
+	};
+
+	char::from_u32(codepoint)
+		.ok_or_else(|| anyhow!("ID {id} is larger than the largest ID {MAX_ID}"))
+}
+
+/// Turns a symbol back into the integer ID it stands for
+pub fn symbol_to_id(symbol: char) -> u32 {
+	match symbol as u32 {
+		codepoint @ 0..SURROGATE_START => codepoint,
+		codepoint => codepoint - SURROGATE_COUNT,
+	}
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+	#[allow(unused)]
+	use test::black_box;
+	#[allow(unused)]
+	use super::{MAX_ID, id_to_symbol, symbol_to_id};
+
+	// Map IDs to symbols and back
+	#[bench]
+	fn id_to_symbol_round_trip(b: &mut Bencher) {
+		for id in [0, 0xD7FF, 0xD800, 0xDFFF, 0xE000, MAX_ID] {
+			let symbol = id_to_symbol(id).expect("Failed to map ID");
+			assert_eq!(symbol_to_id(symbol), id);
+		}
+		assert_eq!(id_to_symbol(0xD800).unwrap(), '\u{E000}');
+		assert!(id_to_symbol(MAX_ID + 1).is_err());
+		assert!(id_to_symbol(u32::MAX).is_err());
+
+		b.iter(|| id_to_symbol(black_box(50_000)));
+	}
+}
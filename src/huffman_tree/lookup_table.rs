@@ -0,0 +1,313 @@
+use fnv::FnvHashMap;
+
+/// The most slots a dense lookup table may have when it is mostly empty
+///
+/// 4096 slots covers ASCII, Latin, Greek, Cyrillic and most other alphabets
+/// while keeping a table of codes under about 100 KiB. A single character above
+/// this, like an emoji at U+1F600, would otherwise force a table of over a
+/// hundred thousand mostly empty slots.
+const MAX_DENSE_SLOTS: usize = 0x1000;
+
+/// The most symbols a remapped lookup table can index with a `u16`
+const MAX_REMAPPED_SYMBOLS: usize = u16::MAX as usize + 1;
+
+/// The density above which a large table is still kept dense
+///
+/// When at least half the slots are filled, a dense table uses at most twice
+/// the memory of its values, which is worth it to skip hashing on lookups.
+const MIN_DENSE_DENSITY: f64 = 0.5;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// How a lookup table stores its entries
+pub enum LookupStrategy {
+	/// Always index a Vec by codepoint, the fastest lookups but the most memory
+	///
+	/// The Vec has a slot for every codepoint up to the largest symbol, so a
+	/// single U+10FFFF costs over a million slots. Only use this when every
+	/// symbol's codepoint is known to be small.
+	DenseVec,
+	/// Always use a hash map, with memory bounded by the alphabet size
+	HashMap,
+	/// Use a dense Vec when it is small or mostly full, and remapped indices
+	/// otherwise
+	#[default]
+	Auto,
+}
+
+#[derive(Clone)]
+/// A table from characters to values
+///
+/// Small alphabets are stored in a Vec indexed by codepoint, which avoids
+/// running a hash function on every lookup. Alphabets with high codepoints are
+/// remapped instead: each symbol gets a dense `u16` index into Vecs of symbols
+/// and values, so memory is bounded by the alphabet size rather than the
+/// largest codepoint. A plain hash map can also be chosen with
+/// `LookupStrategy::HashMap`.
+pub enum LookupTable<T> {
+	Dense(Vec<Option<T>>),
+	Remapped {
+		indices: FnvHashMap<char, u16>,
+		symbols: Vec<char>,
+		values: Vec<T>,
+	},
+	Hashed(FnvHashMap<char, T>),
+}
+
+impl<T> LookupTable<T> {
+	/// Constructs a lookup table, choosing a representation by largest codepoint
+	///
+	/// Alphabets too large for `u16` indices are always dense.
+	pub fn new(map: FnvHashMap<char, T>) -> Self {
+		Self::new_with_strategy(map, LookupStrategy::Auto)
+	}
+
+	/// Constructs a lookup table with the representation a strategy picks
+	///
+	/// A map with a codepoint too large to index by is never dense, even with
+	/// `LookupStrategy::DenseVec`.
+	pub fn new_with_strategy(map: FnvHashMap<char, T>, strategy: LookupStrategy) -> Self {
+		match (strategy, Self::slots(&map)) {
+			(LookupStrategy::HashMap, _) => LookupTable::Hashed(map),
+			(_, Some(slots)) if Self::prefers_dense(&map, slots, strategy) => Self::new_dense(map, slots),
+			_ => Self::new_remapped(map),
+		}
+	}
+
+	/// Replaces every entry, reusing the current allocation where possible
+	///
+	/// A dense table stays dense and is cleared and refilled in place when the
+	/// new map would also be dense. Otherwise the table is rebuilt.
+	pub fn refill(&mut self, map: FnvHashMap<char, T>, strategy: LookupStrategy) {
+		let slots = Self::slots(&map);
+
+		match (self, slots) {
+			(LookupTable::Dense(table), Some(slots)) if Self::prefers_dense(&map, slots, strategy) => {
+				table.clear();
+				table.resize_with(slots, || None);
+				Self::fill_dense(table, map);
+			}
+			(table, _) => *table = Self::new_with_strategy(map, strategy)
+		}
+	}
+
+	/// Gets the number of slots a dense table would need for a map
+	///
+	/// Returns None when a codepoint cannot be a `usize` index, which can only
+	/// happen on platforms where `usize` is 16 bits.
+	fn slots(map: &FnvHashMap<char, T>) -> Option<usize> {
+		map.keys().try_fold(0, |slots, character| {
+			Some(slots.max(dense_index(*character)?.checked_add(1)?))
+		})
+	}
+
+	/// Checks whether a map should be stored as a dense table
+	fn prefers_dense(map: &FnvHashMap<char, T>, slots: usize, strategy: LookupStrategy) -> bool {
+		match strategy {
+			LookupStrategy::DenseVec => true,
+			LookupStrategy::HashMap => false,
+			LookupStrategy::Auto => {
+				slots <= MAX_DENSE_SLOTS
+					|| map.len() > MAX_REMAPPED_SYMBOLS
+					|| map.len() as f64 / slots as f64 >= MIN_DENSE_DENSITY
+			}
+		}
+	}
+
+	/// Constructs a remapped lookup table with symbols in codepoint order
+	fn new_remapped(map: FnvHashMap<char, T>) -> Self {
+		let mut entries: Vec<(char, T)> = map.into_iter().collect();
+		entries.sort_unstable_by_key(|(character, _)| *character);
+
+		let (symbols, values): (Vec<char>, Vec<T>) = entries.into_iter().unzip();
+		let indices = symbols.iter()
+			.enumerate()
+			.map(|(index, character)| (*character, index as u16))
+			.collect();
+
+		LookupTable::Remapped {indices, symbols, values}
+	}
+
+	/// Constructs a dense lookup table with a set number of slots
+	fn new_dense(map: FnvHashMap<char, T>, slots: usize) -> Self {
+		let mut table: Vec<Option<T>> = Vec::with_capacity(slots);
+		table.resize_with(slots, || None);
+		Self::fill_dense(&mut table, map);
+
+		LookupTable::Dense(table)
+	}
+
+	/// Puts each entry of a map in its slot of a dense table
+	///
+	/// The table must already have a slot for every codepoint in the map.
+	fn fill_dense(table: &mut [Option<T>], map: FnvHashMap<char, T>) {
+		for (character, value) in map {
+			if let Some(slot) = dense_index(character).and_then(|index| table.get_mut(index)) {
+				*slot = Some(value);
+			}
+		}
+	}
+
+	/// Gets the value for a character
+	pub fn get(&self, character: char) -> Option<&T> {
+		match self {
+			LookupTable::Dense(table) => table.get(dense_index(character)?)?.as_ref(),
+			LookupTable::Remapped {indices, values, ..} => {
+				values.get(*indices.get(&character)? as usize)
+			}
+			LookupTable::Hashed(map) => map.get(&character),
+		}
+	}
+
+	/// Iterates over every character and its value in codepoint order
+	pub fn iter(&self) -> impl Iterator<Item = (char, &T)> {
+		let dense = match self {
+			LookupTable::Dense(table) => Some(table.iter().enumerate().filter_map(|(index, value)| {
+				Some((char::from_u32(index as u32)?, value.as_ref()?))
+			})),
+			_ => None
+		};
+
+		let remapped = match self {
+			LookupTable::Remapped {symbols, values, ..} => Some(symbols.iter().copied().zip(values)),
+			_ => None
+		};
+
+		let hashed = match self {
+			LookupTable::Hashed(map) => {
+				let mut entries: Vec<(char, &T)> = map.iter()
+					.map(|(character, value)| (*character, value))
+					.collect();
+				entries.sort_unstable_by_key(|(character, _)| *character);
+				Some(entries.into_iter())
+			}
+			_ => None
+		};
+
+		dense.into_iter().flatten()
+			.chain(remapped.into_iter().flatten())
+			.chain(hashed.into_iter().flatten())
+	}
+
+	/// Gets the fraction of slots a dense table would fill for these entries
+	///
+	/// This is the number of entries divided by one more than the largest
+	/// codepoint, whatever the current representation. A low density means
+	/// indexing by codepoint would leave most of the table empty.
+	pub fn density(&self) -> f64 {
+		let (filled, slots) = match self {
+			LookupTable::Dense(table) => (table.iter().filter(|value| value.is_some()).count(), table.len() as f64),
+			LookupTable::Remapped {symbols, ..} => (symbols.len(), slots_for(symbols.last().copied())),
+			LookupTable::Hashed(map) => (map.len(), slots_for(map.keys().max().copied())),
+		};
+
+		if slots == 0.0 {
+			return 0.0;
+		}
+
+		filled as f64 / slots
+	}
+
+	/// Iterates over every value
+	pub fn values(&self) -> impl Iterator<Item = &T> {
+		self.iter().map(|(_, value)| value)
+	}
+}
+
+/// Gets the slot of a dense table a character goes in
+///
+/// This is its codepoint, or None if that does not fit in a `usize`.
+fn dense_index(character: char) -> Option<usize> {
+	usize::try_from(u32::from(character)).ok()
+}
+
+/// Gets the number of slots a dense table up to a largest character would have
+///
+/// This is a float so it can describe tables too large to index on any
+/// platform.
+fn slots_for(largest: Option<char>) -> f64 {
+	largest.map_or(0.0, |character| f64::from(u32::from(character)) + 1.0)
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+	#[allow(unused)]
+	use test::black_box;
+	#[allow(unused)]
+	use super::{LookupStrategy, LookupTable};
+
+	// Build a table for an alphabet
+	#[allow(unused)]
+	fn table(alphabet: impl Iterator<Item = char>) -> LookupTable<u32> {
+		let map: fnv::FnvHashMap<char, u32> = alphabet
+			.map(|character| (character, character as u32))
+			.collect();
+
+		LookupTable::new(map)
+	}
+
+	// Estimate the bytes a table allocates, not counting its values' own heap
+	#[allow(unused)]
+	fn table_bytes<T>(table: &LookupTable<T>) -> usize {
+		match table {
+			LookupTable::Dense(values) => values.capacity() * std::mem::size_of::<Option<T>>(),
+			LookupTable::Remapped {indices, symbols, values} => {
+				indices.capacity() * std::mem::size_of::<(char, u16)>()
+					+ symbols.capacity() * std::mem::size_of::<char>()
+					+ values.capacity() * std::mem::size_of::<T>()
+			}
+			LookupTable::Hashed(map) => map.capacity() * std::mem::size_of::<(char, T)>(),
+		}
+	}
+
+	// Look up a character in a dense table
+	#[bench]
+	fn dense_get(b: &mut Bencher) {
+		let dense = table(('a'..='z').chain('A'..='Z'));
+		assert!(matches!(dense, LookupTable::Dense(_)));
+		assert_eq!(dense.density(), 52.0 / ('z' as usize + 1) as f64);
+
+		b.iter(|| dense.get(black_box('Q')));
+	}
+
+	// Look up a character in a remapped table
+	#[bench]
+	fn remapped_get(b: &mut Bencher) {
+		let remapped = table(('a'..='z').chain('😀'..='🙏'));
+		assert!(matches!(remapped, LookupTable::Remapped {..}));
+
+		b.iter(|| remapped.get(black_box('Q')));
+	}
+
+	// Look up a character in a hash map table
+	#[bench]
+	fn hashed_get(b: &mut Bencher) {
+		let alphabet = || ('a'..='z').chain('A'..='Z').map(|character| (character, character as u32));
+		let hashed = LookupTable::new_with_strategy(alphabet().collect(), LookupStrategy::HashMap);
+		assert!(matches!(hashed, LookupTable::Hashed(_)));
+		assert!(hashed.iter().eq(table(('a'..='z').chain('A'..='Z')).iter()));
+
+		b.iter(|| hashed.get(black_box('Q')));
+	}
+
+	// Keep a large but mostly full alphabet dense
+	#[bench]
+	fn dense_large_alphabet(b: &mut Bencher) {
+		let dense = table('\u{4E00}'..='\u{9FFF}');
+		assert!(matches!(dense, LookupTable::Dense(_)));
+
+		b.iter(|| dense.get(black_box('\u{6C34}')));
+	}
+
+	// Compare memory for emoji-heavy text
+	#[bench]
+	fn remapped_memory(b: &mut Bencher) {
+		let alphabet = || ('a'..='z').chain('😀'..='🙏');
+		let remapped = table(alphabet());
+		let dense = LookupTable::new_dense(alphabet().map(|character| (character, character as u32)).collect(), '🙏' as usize + 1);
+
+		assert!(table_bytes(&remapped) * 100 < table_bytes(&dense));
+		assert_eq!(remapped.density(), dense.density());
+		b.iter(|| table(black_box(alphabet())));
+	}
+}
@@ -1,5 +1,45 @@
+mod bit_convention;
+mod bit_order;
+mod bit_reader;
+mod bit_writer;
 mod bits;
+mod canonical;
+mod codec;
+mod decode_iter;
+mod decode_state;
+mod decoder;
+mod english;
+mod frequencies;
+mod frequency_counter;
 mod huffman_node;
+#[allow(clippy::module_inception)]
 mod huffman_tree;
+mod ids;
+mod lookup_table;
+mod ngram;
+mod packed_code;
+mod prefix;
+mod symbol;
+mod test_vectors;
+mod tie_break;
+mod windowed;
 
+pub type BitConvention = bit_convention::BitConvention;
+pub use bit_order::BitOrder;
+pub type Bits = bits::Bits;
+pub use bit_reader::BitReader;
+pub use bit_writer::BitWriter;
+pub use codec::Codec;
+pub use decode_state::DecodeState;
 pub type HuffmanTree = huffman_tree::HuffmanTree;
+pub(crate) use huffman_tree::get_letter_frequencies;
+pub use frequencies::{Frequencies, Probabilities, entropy, estimate_savings, frequencies_as_probabilities, load_frequencies};
+pub use frequency_counter::FrequencyCounter;
+pub use lookup_table::LookupStrategy;
+pub use ngram::NgramTree;
+pub use prefix::is_prefix_free;
+pub use symbol::escape_symbol;
+pub use tie_break::TieBreak;
+pub use windowed::{WindowedEncoder, decode_windowed};
+#[cfg(feature = "test-internals")]
+pub use huffman_node::Node;
@@ -0,0 +1,143 @@
+use anyhow::{anyhow, Result, Context};
+use fnv::FnvHashMap;
+use super::bits::Bits;
+use super::frequencies::Frequencies;
+use super::huffman_tree::HuffmanTree;
+use super::ids::{id_to_symbol, symbol_to_id};
+
+/// A Huffman tree over groups of `n` characters instead of single characters
+///
+/// Text with strong links between neighbouring characters, like `th` and `he`
+/// in English, compresses better when those groups get their own codes. The
+/// cost is a codebook with many more entries, which matters whenever the tree
+/// has to be sent alongside the encoded text.
+///
+/// Text is split into grams of `n` characters from the start. When its length
+/// is not a multiple of `n`, the last gram is shorter and is its own entry in
+/// the codebook.
+pub struct NgramTree {
+	n: usize,
+	tree: HuffmanTree,
+	grams: Vec<String>,
+	symbols: FnvHashMap<String, char>,
+}
+
+impl NgramTree {
+	/// Construct a new n-gram tree from example text
+	///
+	/// Each distinct gram is given a stand-in symbol and the tree is built over
+	/// those. This will error if `n` is 0 or the text is empty.
+	pub fn new(example_text: &str, n: usize) -> Result<Self> {
+		if n == 0 {
+			return Err(anyhow!("Grams must be at least 1 character long"));
+		}
+
+		let mut grams = Vec::new();
+		let mut symbols = FnvHashMap::default();
+		let mut frequencies = Frequencies::default();
+
+		for gram in split_grams(example_text, n) {
+			let symbol = match symbols.get(gram) {
+				Some(symbol) => *symbol,
+				None => {
+					let symbol = id_to_symbol(grams.len() as u32)
+						.context("Too many distinct grams")?;
+					grams.push(gram.to_string());
+					symbols.insert(gram.to_string(), symbol);
+					symbol
+				}
+			};
+
+			*frequencies.entry(symbol).or_insert(0) += 1;
+		}
+
+		let tree = HuffmanTree::from_frequencies(frequencies)?;
+		Ok(NgramTree {n, tree, grams, symbols})
+	}
+
+	/// Gets the number of characters in each gram
+	pub fn n(&self) -> usize {
+		self.n
+	}
+
+	/// Gets the number of distinct grams in the codebook
+	pub fn gram_count(&self) -> usize {
+		self.grams.len()
+	}
+
+	/// Encodes a string one gram at a time
+	///
+	/// This will fail if the string has a gram that was not in the example
+	/// text, including a shorter final gram.
+	pub fn encode(&self, string: &str) -> Result<Bits> {
+		let symbols = split_grams(string, self.n)
+			.map(|gram| {
+				self.symbols.get(gram).copied()
+					.with_context(|| format!("Gram {gram:?} not found in codebook"))
+			})
+			.collect::<Result<String>>()?;
+
+		self.tree.encode(&symbols)
+	}
+
+	/// Decodes a Bits object back into a string
+	///
+	/// This fails in the same cases as `HuffmanTree::decode`.
+	pub fn decode(&self, bits: &Bits) -> Result<String> {
+		let mut decoded = String::new();
+
+		for symbol in self.tree.decode(bits)?.chars() {
+			decoded.push_str(&self.grams[symbol_to_id(symbol) as usize]);
+		}
+
+		Ok(decoded)
+	}
+}
+
+/// Splits text into grams of `n` characters, with a shorter final gram
+fn split_grams(text: &str, n: usize) -> impl Iterator<Item = &str> {
+	let mut rest = text;
+
+	std::iter::from_fn(move || {
+		if rest.is_empty() {
+			return None;
+		}
+
+		let end = rest.char_indices().nth(n).map_or(rest.len(), |(index, _)| index);
+		let (gram, tail) = rest.split_at(end);
+		rest = tail;
+		Some(gram)
+	})
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+	#[allow(unused)]
+	use super::NgramTree;
+
+	// Encode text as bigrams
+	#[bench]
+	fn ngram_encode(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let bigrams = NgramTree::new(&text, 2)
+			.expect("Failed to build n-gram tree");
+		let unigrams = super::HuffmanTree::new(&text)
+			.expect("Failed to build Huffman tree");
+
+		let bits = bigrams.encode(&text).expect("Failed to encode message");
+		assert_eq!(bigrams.decode(&bits).expect("Failed to decode message"), text);
+		assert!(bits.len() < unigrams.encode(&text).expect("Failed to encode message").len());
+		assert!(bigrams.gram_count() > unigrams.alphabet_size());
+
+		let odd = NgramTree::new("abcdé", 2).expect("Failed to build n-gram tree");
+		assert_eq!(odd.gram_count(), 3);
+		assert_eq!(odd.decode(&odd.encode("abcdé").unwrap()).unwrap(), "abcdé");
+		assert_eq!(odd.decode(&odd.encode("cdab").unwrap()).unwrap(), "cdab");
+		assert!(odd.encode("abc").is_err());
+		assert!(NgramTree::new("abc", 0).is_err());
+
+		b.iter(|| bigrams.encode(&text));
+	}
+}
@@ -0,0 +1,27 @@
+use super::bits::Bits;
+
+/// The longest code that can be packed
+pub const MAX_PACKED_BITS: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A code stored inline as an integer and a length
+///
+/// The code is the low `len` bits of `bits`, with its first bit the most
+/// significant. Unlike Bits this needs no heap allocation, so writing a code is
+/// a shift and an or rather than a loop over a Vec.
+pub struct PackedCode {
+	pub bits: u32,
+	pub len: u8,
+}
+
+impl PackedCode {
+	/// Packs a code, or gives None when it is longer than 32 bits
+	pub fn from_bits(code: &Bits) -> Option<Self> {
+		if code.len() > MAX_PACKED_BITS {
+			return None;
+		}
+
+		let bits = code.iter().fold(0, |packed, bit| packed << 1 | u32::from(bit));
+		Some(PackedCode {bits, len: code.len() as u8})
+	}
+}
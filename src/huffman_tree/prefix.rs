@@ -0,0 +1,54 @@
+use super::bits::Bits;
+
+/// Marks a missing child in the trie, since the root is never a child
+const NO_CHILD: usize = 0;
+
+/// A binary trie of codes stored flat, one entry per node
+///
+/// Nodes refer to their children by index rather than owning them, so
+/// dropping the trie frees two Vecs instead of recursing down a chain of
+/// boxes one bit deep per level, which overflows the stack on long codes.
+struct Trie {
+	children: Vec<[usize; 2]>,
+	terminal: Vec<bool>,
+}
+
+/// Checks whether no code in a codebook is a prefix of another
+///
+/// Huffman codes are always prefix-free, but externally supplied codes may not
+/// be, and decoding them would be ambiguous. Duplicate codes count as prefixes
+/// of each other.
+pub fn is_prefix_free(codes: &[Bits]) -> bool {
+	let mut trie = Trie {children: vec![[NO_CHILD; 2]], terminal: vec![false]};
+	codes.iter().all(|code| insert(&mut trie, code))
+}
+
+/// Inserts a code into the trie
+///
+/// Returns false when the code collides with one inserted earlier, either by
+/// passing through the end of that code or by ending on its path.
+fn insert(trie: &mut Trie, code: &Bits) -> bool {
+	let mut node = 0;
+
+	for bit in code.iter() {
+		if trie.terminal[node] {
+			return false;
+		}
+
+		node = match trie.children[node][bit as usize] {
+			NO_CHILD => {
+				let child = trie.children.len();
+				trie.children.push([NO_CHILD; 2]);
+				trie.terminal.push(false);
+				trie.children[node][bit as usize] = child;
+				child
+			}
+			child => child
+		};
+	}
+
+	let collision = trie.terminal[node] || trie.children[node] != [NO_CHILD; 2];
+	trie.terminal[node] = true;
+
+	!collision
+}
@@ -0,0 +1,69 @@
+use anyhow::{anyhow, Result};
+
+/// Writes a symbol so that it is readable on one line
+///
+/// Tabs, newlines, carriage returns and backslashes become `\t`, `\n`, `\r`
+/// and `\\`. Any other control or whitespace character except the space is
+/// written as its codepoint, like `\u{1B}`. Everything else is unchanged.
+/// `unescape_symbol` reverses this.
+pub fn escape_symbol(symbol: char) -> String {
+	match symbol {
+		'\t' => "\\t".to_string(),
+		'\n' => "\\n".to_string(),
+		'\r' => "\\r".to_string(),
+		'\\' => "\\\\".to_string(),
+		' ' => " ".to_string(),
+		_ if symbol.is_control() || symbol.is_whitespace() => format!("\\u{{{:X}}}", symbol as u32),
+		_ => symbol.to_string()
+	}
+}
+
+/// Turns an escaped symbol field back into its character
+pub fn unescape_symbol(field: &str) -> Result<char> {
+	if let Some(hex) = field.strip_prefix("\\u{").and_then(|rest| rest.strip_suffix('}')) {
+		return u32::from_str_radix(hex, 16).ok()
+			.and_then(char::from_u32)
+			.ok_or_else(|| anyhow!("Invalid codepoint escape in symbol"));
+	}
+
+	let mut chars = field.chars();
+
+	let symbol = match (chars.next(), chars.next()) {
+		(Some('\\'), Some('t')) => '\t',
+		(Some('\\'), Some('n')) => '\n',
+		(Some('\\'), Some('r')) => '\r',
+		(Some('\\'), Some('\\')) => '\\',
+		(Some('\\'), _) => return Err(anyhow!("Unknown escape in symbol")),
+		(Some(symbol), None) => return Ok(symbol),
+		_ => return Err(anyhow!("Symbol must be a single character"))
+	};
+
+	match chars.next() {
+		Some(_) => Err(anyhow!("Symbol must be a single character")),
+		None => Ok(symbol)
+	}
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+	#[allow(unused)]
+	use test::black_box;
+	#[allow(unused)]
+	use super::{escape_symbol, unescape_symbol};
+
+	// Escape and unescape symbols
+	#[bench]
+	fn escape_symbol_round_trip(b: &mut Bencher) {
+		let symbols = ['a', ' ', '\t', '\n', '\r', '\\', '\0', '\u{1B}', '\u{7F}', '\u{A0}', '\u{2028}', 'é', '😀'];
+		for symbol in symbols {
+			let escaped = escape_symbol(symbol);
+			assert!(escaped.chars().all(|c| c == ' ' || !(c.is_control() || c.is_whitespace())));
+			assert_eq!(unescape_symbol(&escaped).expect("Failed to unescape symbol"), symbol);
+		}
+		assert_eq!(escape_symbol('\u{1B}'), "\\u{1B}");
+		assert!(unescape_symbol("\\u{D800}").is_err());
+
+		b.iter(|| symbols.map(|symbol| escape_symbol(black_box(symbol))));
+	}
+}
@@ -0,0 +1,87 @@
+// Cross-checks code lengths against known vectors in `huffman-test-vectors.txt`
+//
+// Matching lengths mean the canonical codebook, and so every encoded bit, is
+// the same as any other canonical Huffman implementation would produce.
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+	#[allow(unused)]
+	use test::black_box;
+	#[allow(unused)]
+	use anyhow::{anyhow, Context, Result};
+	#[allow(unused)]
+	use super::super::{canonical::canonical_codes, huffman_tree::HuffmanTree, symbol::unescape_symbol};
+
+	// An input and the code lengths its tree must have
+	#[allow(unused)]
+	struct TestVector {
+		input: String,
+		lengths: Vec<(char, usize)>,
+	}
+
+	// Load every vector from a fixture, skipping comments and blank lines
+	#[allow(unused)]
+	fn load_test_vectors(path: &str) -> Result<Vec<TestVector>> {
+		let contents = std::fs::read_to_string(path)
+			.context("Failed to read test vectors")?;
+
+		contents.lines()
+			.enumerate()
+			.filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+			.map(|(index, line)| {
+				parse_test_vector(line)
+					.with_context(|| format!("Invalid test vector on line {}", index + 1))
+			})
+			.collect()
+	}
+
+	// Parse an `input<TAB>symbol:length ...` line
+	#[allow(unused)]
+	fn parse_test_vector(line: &str) -> Result<TestVector> {
+		let (input, lengths) = line.split_once('\t')
+			.ok_or_else(|| anyhow!("Missing tab between input and lengths"))?;
+
+		let mut lengths = lengths.split(' ')
+			.map(|entry| {
+				let (symbol, length) = entry.rsplit_once(':')
+					.ok_or_else(|| anyhow!("Missing colon in {entry:?}"))?;
+				Ok((unescape_symbol(symbol)?, length.parse().context("Invalid code length")?))
+			})
+			.collect::<Result<Vec<(char, usize)>>>()?;
+		lengths.sort_unstable();
+
+		Ok(TestVector {input: input.to_string(), lengths})
+	}
+
+	// Check every vector's code lengths and canonical codebook
+	#[bench]
+	fn test_vectors(b: &mut Bencher) {
+		let vectors = load_test_vectors("huffman-test-vectors.txt")
+			.expect("Failed to load test vectors");
+		assert_eq!(vectors.len(), 6);
+
+		for vector in &vectors {
+			let tree = HuffmanTree::new(&vector.input)
+				.expect("Failed to build Huffman tree");
+			assert_eq!(tree.code_lengths(), vector.lengths, "Code lengths differ for {:?}", vector.input);
+
+			let canonical = HuffmanTree::from_code_lengths(&vector.lengths)
+				.expect("Failed to build canonical tree");
+			let codes = canonical_codes(&vector.lengths)
+				.expect("Failed to assign canonical codes");
+			for (character, code) in &codes {
+				assert_eq!(canonical.try_code(*character), Some(code), "Canonical code differs for {character:?}");
+			}
+
+			let bits = canonical.encode(&vector.input)
+				.expect("Failed to encode message");
+			assert_eq!(canonical.decode(&bits).expect("Failed to decode message"), vector.input);
+		}
+
+		assert!(parse_test_vector("ab a:1 b:1").is_err());
+		assert!(parse_test_vector("ab\ta1").is_err());
+
+		b.iter(|| vectors.iter().map(|vector| HuffmanTree::new(black_box(&vector.input))).collect::<Result<Vec<_>>>());
+	}
+}
@@ -0,0 +1,46 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// How symbols with equal frequencies are ordered while building a tree
+///
+/// Any order gives an optimal code, but different orders can give different
+/// codes and code lengths.
+pub enum TieBreak {
+	/// Tied symbols are ordered by codepoint, so a text always builds the same tree
+	#[default]
+	Symbol,
+	/// Tied symbols are ordered by a shuffle seeded with this value
+	///
+	/// The same seed always builds the same tree, while different seeds can
+	/// build different trees of the same total cost.
+	Seeded(u64),
+}
+
+impl TieBreak {
+	/// Sorts leaves from least to most frequent, breaking ties by this rule
+	pub fn sort(self, leaves: &mut [(char, usize)]) {
+		leaves.sort_unstable_by_key(|(character, count)| (*count, *character));
+
+		if let TieBreak::Seeded(seed) = self {
+			let mut state = seed;
+			let mut keyed: Vec<_> = leaves.iter()
+				.map(|leaf| (*leaf, splitmix64(&mut state)))
+				.collect();
+
+			keyed.sort_unstable_by_key(|((_, count), key)| (*count, *key));
+			leaves.iter_mut()
+				.zip(keyed)
+				.for_each(|(slot, (leaf, _))| *slot = leaf);
+		}
+	}
+}
+
+/// Generates the next number from a SplitMix64 sequence
+///
+/// This is a tiny, well-mixed generator that is plenty for shuffling ties.
+fn splitmix64(state: &mut u64) -> u64 {
+	*state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+	let mut z = *state;
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+	z ^ (z >> 31)
+}
@@ -0,0 +1,166 @@
+use anyhow::{anyhow, Result, Context};
+use super::bits::Bits;
+use super::huffman_tree::HuffmanTree;
+
+/// Bits used for the alphabet size and symbol count of a window
+const COUNT_BITS: usize = 32;
+/// Bits used for each symbol's codepoint, enough for U+10FFFF
+const CODEPOINT_BITS: usize = 21;
+/// Bits used for each symbol's code length
+const LENGTH_BITS: usize = 8;
+
+/// Encodes text in windows, building a new tree for each window
+///
+/// Text whose statistics drift compresses poorly with one tree. This splits
+/// the text into windows of a set number of symbols and trains a canonical
+/// tree on each one, a middle ground between a static and a fully adaptive
+/// code.
+///
+/// Each window is written as a `1` marker bit, the tree's code lengths, the
+/// number of symbols and the encoded symbols. A `0` marker bit ends the
+/// stream. `decode_windowed` reads the markers and rebuilds each tree in turn.
+pub struct WindowedEncoder {
+	window_size: usize,
+	window: String,
+	window_symbols: usize,
+	output: Bits,
+}
+
+impl WindowedEncoder {
+	/// Constructs an encoder that builds a new tree every `window_size` symbols
+	///
+	/// This will error if the window size is 0.
+	pub fn new(window_size: usize) -> Result<Self> {
+		if window_size == 0 {
+			return Err(anyhow!("Window size must be at least 1"));
+		}
+
+		Ok(WindowedEncoder {window_size, window: String::new(), window_symbols: 0, output: Bits::new()})
+	}
+
+	/// Encodes more text
+	///
+	/// Text is buffered until a window fills, so a window may span several
+	/// calls. Call `finish` once everything has been encoded.
+	pub fn encode(&mut self, text: &str) -> Result<()> {
+		for character in text.chars() {
+			self.window.push(character);
+			self.window_symbols += 1;
+
+			if self.window_symbols == self.window_size {
+				self.flush_window()?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Encodes any partly filled window and ends the stream
+	pub fn finish(mut self) -> Result<Bits> {
+		self.flush_window()?;
+		self.output.add(false);
+
+		Ok(self.output)
+	}
+
+	/// Writes the buffered window with its own tree
+	fn flush_window(&mut self) -> Result<()> {
+		if self.window_symbols == 0 {
+			return Ok(());
+		}
+
+		let lengths = HuffmanTree::new(&self.window)?.code_lengths();
+		let tree = HuffmanTree::from_code_lengths(&lengths)?;
+
+		self.output.add(true);
+		self.output.add_field(lengths.len(), COUNT_BITS)?;
+		for (character, length) in lengths {
+			let codepoint = usize::try_from(u32::from(character))
+				.context("Codepoint does not fit in usize")?;
+			self.output.add_field(codepoint, CODEPOINT_BITS)?;
+			self.output.add_field(length, LENGTH_BITS)
+				.context("Code is too long to store")?;
+		}
+		self.output.add_field(self.window_symbols, COUNT_BITS)?;
+		tree.encode_into(&self.window, &mut self.output)?;
+
+		self.window.clear();
+		self.window_symbols = 0;
+		Ok(())
+	}
+}
+
+/// Decodes a stream written by a `WindowedEncoder`
+///
+/// This will fail if the stream is cut short, has an invalid tree, or has
+/// bits that do not match a window's tree.
+pub fn decode_windowed(bits: &Bits) -> Result<String> {
+	let mut decoded = String::new();
+	let mut position = 0;
+
+	while next_field(bits, &mut position, 1)? == 1 {
+		let alphabet_size = next_field(bits, &mut position, COUNT_BITS)?;
+		let lengths = (0..alphabet_size)
+			.map(|_| {
+				let codepoint = next_field(bits, &mut position, CODEPOINT_BITS)?;
+				let character = char::from_u32(codepoint as u32)
+					.context("Invalid symbol in window tree")?;
+				Ok((character, next_field(bits, &mut position, LENGTH_BITS)?))
+			})
+			.collect::<Result<Vec<_>>>()?;
+		let tree = HuffmanTree::from_code_lengths(&lengths)?;
+
+		let symbols = next_field(bits, &mut position, COUNT_BITS)?;
+		for _ in 0..symbols {
+			let (character, length) = tree.walk_symbol(bits, position)?;
+			decoded.push(character);
+			position += length;
+		}
+	}
+
+	Ok(decoded)
+}
+
+/// Gets the field at the position with `Bits::get_field`, moving past it
+fn next_field(bits: &Bits, position: &mut usize, width: usize) -> Result<usize> {
+	let value = bits.get_field(*position, width)?;
+	*position += width;
+
+	Ok(value)
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+	#[allow(unused)]
+	use super::{WindowedEncoder, decode_windowed};
+
+	// Encode a file in windows
+	#[bench]
+	fn windowed_encode(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+
+		let mut encoder = WindowedEncoder::new(1000).expect("Failed to build encoder");
+		let (first, second) = text.split_at(text.len() / 3);
+		encoder.encode(first).expect("Failed to encode message");
+		encoder.encode(second).expect("Failed to encode message");
+		let bits = encoder.finish().expect("Failed to finish encoding");
+		assert_eq!(decode_windowed(&bits).expect("Failed to decode message"), text);
+
+		let mut truncated = super::Bits::new();
+		bits.iter().take(bits.len() - 1).for_each(|bit| truncated.add(bit));
+		assert!(decode_windowed(&truncated).is_err());
+
+		let empty = WindowedEncoder::new(1000).expect("Failed to build encoder")
+			.finish().expect("Failed to finish encoding");
+		assert_eq!(decode_windowed(&empty).expect("Failed to decode message"), "");
+		assert!(WindowedEncoder::new(0).is_err());
+
+		b.iter(|| {
+			let mut encoder = WindowedEncoder::new(1000)?;
+			encoder.encode(&text)?;
+			encoder.finish()
+		});
+	}
+}
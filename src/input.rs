@@ -0,0 +1,138 @@
+use anyhow::{anyhow, Result, Context};
+use std::{fs, io::Read, path::Path};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// How the bytes of an input file are turned into symbols
+pub enum InputMode {
+	/// The file must be valid UTF-8, or reading it fails
+	#[default]
+	Strict,
+	/// Invalid UTF-8 is replaced with U+FFFD and counted
+	Lossy,
+	/// Every byte is its own symbol, the character U+0000 to U+00FF
+	///
+	/// This reads any file, binary or not. Text with characters outside ASCII
+	/// is split into its UTF-8 bytes, so a tree trained this way has at most
+	/// 256 symbols and encodes the same bits as `encode_bytes`.
+	Bytes,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The symbols read from an input file
+pub struct Input {
+	pub text: String,
+	/// The number of invalid UTF-8 sequences replaced while reading
+	pub decode_errors: usize,
+}
+
+/// Reads a file as text using an input mode
+///
+/// Only `InputMode::Strict` fails on a file that is not valid UTF-8. The
+/// other modes always give text, so binary and mis-encoded files can still be
+/// trained on and encoded.
+pub fn read_input<P: AsRef<Path>>(path: P, mode: InputMode) -> Result<Input> {
+	let bytes = fs::read(path)
+		.context("Failed to read input file")?;
+
+	decode_input(bytes, mode)
+}
+
+/// Reads everything from a reader, like stdin, as text using an input mode
+///
+/// This fails in the same cases as `read_input`.
+pub fn read_input_from<R: Read>(mut reader: R, mode: InputMode) -> Result<Input> {
+	let mut bytes = Vec::new();
+	reader.read_to_end(&mut bytes)
+		.context("Failed to read input")?;
+
+	decode_input(bytes, mode)
+}
+
+/// Turns the bytes of an input into text using an input mode
+fn decode_input(bytes: Vec<u8>, mode: InputMode) -> Result<Input> {
+	match mode {
+		InputMode::Strict => {
+			let text = String::from_utf8(bytes)
+				.map_err(|error| anyhow!("Input is not valid UTF-8: {}", error.utf8_error()))?;
+			Ok(Input {text, decode_errors: 0})
+		}
+		InputMode::Lossy => {
+			let mut text = String::with_capacity(bytes.len());
+			let mut decode_errors = 0;
+
+			for chunk in bytes.utf8_chunks() {
+				text.push_str(chunk.valid());
+				if !chunk.invalid().is_empty() {
+					text.push(char::REPLACEMENT_CHARACTER);
+					decode_errors += 1;
+				}
+			}
+
+			Ok(Input {text, decode_errors})
+		}
+		InputMode::Bytes => {
+			let text = bytes.iter().copied().map(char::from).collect();
+			Ok(Input {text, decode_errors: 0})
+		}
+	}
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+	#[allow(unused)]
+	use test::black_box;
+	#[allow(unused)]
+	use super::{InputMode, read_input, read_input_from};
+
+	// Read a file lossily
+	#[bench]
+	fn read_input_lossy(b: &mut Bencher) {
+		let strict = read_input("2022_fall-eecs660-pa2-input.txt", InputMode::Strict)
+			.expect("Failed to read file");
+		let lossy = read_input("2022_fall-eecs660-pa2-input.txt", InputMode::Lossy)
+			.expect("Failed to read file");
+		assert_eq!(lossy, strict);
+
+		b.iter(|| read_input("2022_fall-eecs660-pa2-input.txt", InputMode::Lossy));
+	}
+
+	// Read and encode a file that is not UTF-8
+	#[bench]
+	fn read_input_invalid(b: &mut Bencher) {
+		let path = std::env::temp_dir().join("huffman-read-input-invalid.bin");
+		std::fs::write(&path, b"ab\xFFc\xC3").expect("Failed to write file");
+
+		assert!(read_input(&path, InputMode::Strict).is_err());
+
+		let lossy = read_input(&path, InputMode::Lossy).expect("Failed to read file");
+		assert_eq!(lossy.text, "ab\u{FFFD}c\u{FFFD}");
+		assert_eq!(lossy.decode_errors, 2);
+
+		let bytes = read_input(&path, InputMode::Bytes).expect("Failed to read file");
+		let tree = crate::HuffmanTree::new(&bytes.text).expect("Failed to build Huffman tree");
+		assert_eq!(
+			tree.encode(&bytes.text).expect("Failed to encode message"),
+			tree.encode_bytes(b"ab\xFFc\xC3").expect("Failed to encode message"),
+		);
+
+		b.iter(|| read_input(&path, InputMode::Bytes));
+	}
+
+	// Read text from a reader instead of a file
+	#[bench]
+	fn read_input_from_reader(b: &mut Bencher) {
+		let bytes = std::fs::read("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+		let file = read_input("2022_fall-eecs660-pa2-input.txt", InputMode::Strict)
+			.expect("Failed to read file");
+		assert_eq!(read_input_from(bytes.as_slice(), InputMode::Strict).expect("Failed to read input"), file);
+
+		let lossy = read_input_from(&b"a\xFFb"[..], InputMode::Lossy).expect("Failed to read input");
+		assert_eq!(lossy.text, "a\u{FFFD}b");
+		assert_eq!(lossy.decode_errors, 1);
+
+		b.bytes = bytes.len() as u64;
+		b.iter(|| read_input_from(black_box(bytes.as_slice()), InputMode::Lossy));
+	}
+}
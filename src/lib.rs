@@ -0,0 +1,22 @@
+#![feature(test)]
+extern crate test;
+
+mod compress;
+mod container;
+mod crc32;
+mod huffman_tree;
+mod input;
+mod method;
+#[cfg(feature = "test-internals")]
+mod round_trip;
+
+pub use compress::{CompressionStats, compress_file, decompress_file, run};
+pub use container::{compress, decompress};
+pub use input::{Input, InputMode, read_input, read_input_from};
+pub use method::{Method, suggest_method};
+pub(crate) use huffman_tree::get_letter_frequencies;
+pub use huffman_tree::{BitConvention, BitOrder, BitReader, BitWriter, Bits, Codec, DecodeState, Frequencies, FrequencyCounter, HuffmanTree, LookupStrategy, NgramTree, Probabilities, TieBreak, WindowedEncoder, decode_windowed, entropy, escape_symbol, estimate_savings, frequencies_as_probabilities, is_prefix_free, load_frequencies};
+#[cfg(feature = "test-internals")]
+pub use huffman_tree::Node;
+#[cfg(feature = "test-internals")]
+pub use round_trip::assert_round_trip;
@@ -1,33 +1,205 @@
 #![feature(test)]
 extern crate test;
 
-mod huffman_tree;
-
-use huffman_tree::HuffmanTree;
-use std::fs;
+use anyhow::{anyhow, Context, Result};
+use huffman::{HuffmanTree, Input, InputMode, compress, decompress, read_input, read_input_from};
+use std::io::{self, Read, Write};
 
 const TEXT_FILE_PATH: &str = "2022_fall-eecs660-pa2-input.txt";
 
+const USAGE: &str = "\
+Usage: huffman [--encode | --decode] [--input-mode MODE] [FILE]
+
+With no FILE, or when FILE is -, read standard input. With no arguments at
+all, read the bundled input file.
+
+  (no mode)   Print the number of bits the input encodes to
+  --encode    Write the input compressed into a container to standard output
+  --decode    Write a container's decompressed text to standard output
+  --input-mode strict|lossy|bytes
+              How to read text that is not valid UTF-8: fail, replace it with
+              U+FFFD, or treat every byte as a symbol. Defaults to lossy.
+              Containers given to --decode are always read as bytes.
+  --help      Print this message";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// What the binary does with its input
+enum Mode {
+	/// Print the number of bits the input encodes to
+	#[default]
+	Count,
+	/// Write the input as a container
+	Encode,
+	/// Write a container's text
+	Decode,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Where the binary reads its input from
+enum Source {
+	/// A file at a path
+	File(String),
+	/// Standard input
+	Stdin,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The parsed command line
+struct Args {
+	mode: Mode,
+	source: Source,
+	input_mode: InputMode,
+}
+
+impl Default for Args {
+	/// Counts the bits of the bundled input file, as the binary did before it
+	/// took arguments
+	fn default() -> Self {
+		Args {mode: Mode::Count, source: Source::File(TEXT_FILE_PATH.to_string()), input_mode: InputMode::Lossy}
+	}
+}
+
+/// Parses the arguments after the program name
+///
+/// Returns None when help was asked for.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Args>> {
+	let args: Vec<String> = args.into_iter().collect();
+	if args.is_empty() {
+		return Ok(Some(Args::default()));
+	}
+
+	let mut mode = None;
+	let mut source = None;
+	let mut input_mode = None;
+	let mut args = args.into_iter();
+
+	while let Some(arg) = args.next() {
+		let new_mode = match arg.as_str() {
+			"--help" | "-h" => return Ok(None),
+			"--input-mode" => {
+				let value = args.next()
+					.context("Expected a value after --input-mode")?;
+				if input_mode.replace(parse_input_mode(&value)?).is_some() {
+					return Err(anyhow!("Expected at most one --input-mode"));
+				}
+				continue;
+			}
+			"--encode" => Mode::Encode,
+			"--decode" => Mode::Decode,
+			"-" if source.is_none() => {
+				source = Some(Source::Stdin);
+				continue;
+			}
+			flag if flag.starts_with('-') && flag != "-" => return Err(anyhow!("Unknown option {flag}")),
+			_ if source.is_none() => {
+				source = Some(Source::File(arg));
+				continue;
+			}
+			_ => return Err(anyhow!("Expected at most one input file")),
+		};
+
+		if mode.replace(new_mode).is_some() {
+			return Err(anyhow!("Expected at most one of --encode and --decode"));
+		}
+	}
+
+	Ok(Some(Args {
+		mode: mode.unwrap_or_default(),
+		source: source.unwrap_or(Source::Stdin),
+		input_mode: input_mode.unwrap_or(InputMode::Lossy),
+	}))
+}
+
+/// Parses the value of `--input-mode`
+fn parse_input_mode(value: &str) -> Result<InputMode> {
+	match value {
+		"strict" => Ok(InputMode::Strict),
+		"lossy" => Ok(InputMode::Lossy),
+		"bytes" => Ok(InputMode::Bytes),
+		_ => Err(anyhow!("Unknown input mode {value}, expected strict, lossy or bytes")),
+	}
+}
+
+/// Reads the raw bytes of the input
+fn read_bytes(source: &Source) -> Result<Vec<u8>> {
+	match source {
+		Source::File(path) => std::fs::read(path).context("Failed to read input file"),
+		Source::Stdin => {
+			let mut bytes = Vec::new();
+			io::stdin().lock().read_to_end(&mut bytes)
+				.context("Failed to read standard input")?;
+			Ok(bytes)
+		}
+	}
+}
+
+/// Reads the input as text using an input mode
+fn read_text(source: &Source, input_mode: InputMode) -> Result<Input> {
+	let input = match source {
+		Source::File(path) => read_input(path, input_mode)?,
+		Source::Stdin => read_input_from(io::stdin().lock(), input_mode)?,
+	};
+
+	if input.decode_errors > 0 {
+		eprintln!("Replaced {} invalid UTF-8 sequences", input.decode_errors);
+	}
+
+	Ok(input)
+}
+
+/// Runs the binary on parsed arguments
+fn run(args: &Args) -> Result<()> {
+	let output = match args.mode {
+		Mode::Count => {
+			let text = read_text(&args.source, args.input_mode)?.text;
+			let (_, bits) = HuffmanTree::new_and_encode(&text)
+				.context("Failed to build Huffman tree and encode message")?;
+
+			let num_bits = bits.len();
+			format!("{num_bits} bits\n").into_bytes()
+		}
+		Mode::Encode => compress(&read_text(&args.source, args.input_mode)?.text)
+			.context("Failed to compress input")?,
+		Mode::Decode => decompress(&read_bytes(&args.source)?, true)
+			.context("Failed to decompress input")?
+			.into_bytes(),
+	};
+
+	io::stdout().lock().write_all(&output)
+		.context("Failed to write output")
+}
+
 fn main() {
-	let text = fs::read_to_string(TEXT_FILE_PATH)
-		.expect("Failed to read file");
-	let tree = HuffmanTree::new(&text)
-		.expect("Failed to build Huffman tree");
-	let bits = tree.encode(&text)
-		.expect("Failed to encode message");
+	let args = match parse_args(std::env::args().skip(1)) {
+		Ok(Some(args)) => args,
+		Ok(None) => {
+			println!("{USAGE}");
+			return;
+		}
+		Err(error) => {
+			eprintln!("{error}\n\n{USAGE}");
+			std::process::exit(2);
+		}
+	};
 
-	let num_bits = bits.len();
-	println!("{num_bits} bits");
+	if let Err(error) = run(&args) {
+		eprintln!("{error:#}");
+		std::process::exit(1);
+	}
 }
 
 mod benchmarks {
 	#[allow(unused)]
 	use test::Bencher;
+	#[allow(unused)]
+	use super::{Args, Mode, Source, parse_args};
+	#[allow(unused)]
+	use huffman::InputMode;
 
 	// Full thing
 	#[bench]
 	fn main(b: &mut Bencher) {
-		b.iter(|| super::main());
+		b.iter(|| super::run(&Args::default()).expect("Failed to run"));
 	}
 
 	// Load file
@@ -38,4 +210,38 @@ mod benchmarks {
 				.expect("Failed to read file")
 		});
 	}
+
+	// Parse command lines
+	#[bench]
+	fn parse_args_modes(b: &mut Bencher) {
+		#[allow(unused)]
+		fn parse(args: &[&str]) -> anyhow::Result<Option<Args>> {
+			parse_args(args.iter().map(|arg| arg.to_string()))
+		}
+
+		assert_eq!(parse(&[]).expect("Failed to parse"), Some(Args::default()));
+		assert_eq!(parse(&["-"]).expect("Failed to parse"), Some(Args {mode: Mode::Count, source: Source::Stdin, input_mode: InputMode::Lossy}));
+		assert_eq!(parse(&["--encode"]).expect("Failed to parse"), Some(Args {mode: Mode::Encode, source: Source::Stdin, input_mode: InputMode::Lossy}));
+		assert_eq!(
+			parse(&["--decode", "in.huff"]).expect("Failed to parse"),
+			Some(Args {mode: Mode::Decode, source: Source::File("in.huff".to_string()), input_mode: InputMode::Lossy}),
+		);
+		assert_eq!(
+			parse(&["in.txt", "--encode"]).expect("Failed to parse"),
+			Some(Args {mode: Mode::Encode, source: Source::File("in.txt".to_string()), input_mode: InputMode::Lossy}),
+		);
+		assert_eq!(parse(&["--encode", "--help"]).expect("Failed to parse"), None);
+		assert!(parse(&["--encode", "--decode"]).is_err());
+		assert!(parse(&["a.txt", "b.txt"]).is_err());
+		assert!(parse(&["--verbose"]).is_err());
+
+		let bytes = parse(&["--encode", "--input-mode", "bytes", "in.bin"]).expect("Failed to parse");
+		assert_eq!(bytes, Some(Args {mode: Mode::Encode, source: Source::File("in.bin".to_string()), input_mode: InputMode::Bytes}));
+		assert_eq!(parse(&["--input-mode", "strict"]).expect("Failed to parse").map(|args| args.input_mode), Some(InputMode::Strict));
+		assert!(parse(&["--input-mode"]).is_err());
+		assert!(parse(&["--input-mode", "utf16"]).is_err());
+		assert!(parse(&["--input-mode", "lossy", "--input-mode", "bytes"]).is_err());
+
+		b.iter(|| parse(&["--decode", "in.huff"]));
+	}
 }
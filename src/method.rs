@@ -0,0 +1,105 @@
+use crate::{entropy, get_letter_frequencies};
+
+/// The longest run a one byte run-length count can describe
+const MAX_RUN: usize = u8::MAX as usize;
+
+/// Bits a container spends on its symbol count and padding count
+const CODEBOOK_HEADER_BITS: usize = 32 + 8;
+
+/// Bits a container spends on each symbol, a `u32` codepoint and `u8` length
+const CODEBOOK_ENTRY_BITS: usize = 32 + 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A way to store text
+pub enum Method {
+	/// Huffman code each character, best for skewed character frequencies
+	Huffman,
+	/// Store each run of a repeated character once with its length, best for
+	/// long runs
+	RunLength,
+	/// Keep the UTF-8 bytes as they are, best for short or near random text
+	Store,
+}
+
+/// Recommends a way to store text by estimating the size of each
+///
+/// * Huffman costs the entropy of the characters, at least 1 bit each, plus a
+///   container's codebook.
+/// * Run-length costs each run's character in UTF-8 and a one byte count, with
+///   runs longer than 255 split.
+/// * Store costs the UTF-8 bytes.
+///
+/// The smallest estimate wins, with ties going to the simpler method. Huffman
+/// gives at least 1 bit to every character, so it compresses long runs poorly,
+/// while run-length expands text with few repeats. Empty text is stored.
+pub fn suggest_method(text: &str) -> Method {
+	let frequencies = get_letter_frequencies(text);
+	let symbols: usize = frequencies.values().sum();
+
+	let store_bits = text.len() * 8;
+	let huffman_bits = (entropy(&frequencies) * symbols as f64).max(symbols as f64)
+		+ (CODEBOOK_HEADER_BITS + CODEBOOK_ENTRY_BITS * frequencies.len()) as f64;
+	let run_length_bits = run_length_bits(text);
+
+	if store_bits as f64 <= huffman_bits && store_bits <= run_length_bits {
+		Method::Store
+	} else if run_length_bits as f64 <= huffman_bits {
+		Method::RunLength
+	} else {
+		Method::Huffman
+	}
+}
+
+/// Counts the bits a run-length encoding of text would take
+fn run_length_bits(text: &str) -> usize {
+	let mut bits = 0;
+	let mut characters = text.chars().peekable();
+
+	while let Some(character) = characters.next() {
+		let mut run: usize = 1;
+		while characters.next_if_eq(&character).is_some() {
+			run += 1;
+		}
+
+		bits += run.div_ceil(MAX_RUN) * (character.len_utf8() + 1) * 8;
+	}
+
+	bits
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+	#[allow(unused)]
+	use test::black_box;
+	#[allow(unused)]
+	use super::{Method, suggest_method};
+
+	// Suggest a method for the input file
+	#[bench]
+	fn suggest_method_text(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+
+		assert_eq!(suggest_method(&text), Method::Huffman);
+		assert_eq!(suggest_method(&"a".repeat(10_000)), Method::RunLength);
+		assert_eq!(suggest_method(&"aaaaaaaabbbbbbbbcccccccc".repeat(100)), Method::Huffman);
+		assert_eq!(suggest_method(&["a", "b", "c"].map(|run| run.repeat(32)).concat().repeat(100)), Method::RunLength);
+		assert_eq!(suggest_method("The quick brown fox"), Method::Store);
+		assert_eq!(suggest_method(""), Method::Store);
+
+		b.bytes = text.len() as u64;
+		b.iter(|| suggest_method(black_box(&text)));
+	}
+
+	// Count run-length bits across split runs
+	#[bench]
+	fn run_length_bits(b: &mut Bencher) {
+		let text = "a".repeat(600) + "éé";
+
+		assert_eq!(super::run_length_bits(&text), 3 * 16 + 24);
+		assert_eq!(super::run_length_bits(""), 0);
+
+		b.iter(|| super::run_length_bits(black_box(&text)));
+	}
+}
@@ -0,0 +1,48 @@
+use crate::{HuffmanTree, compress, decompress};
+
+/// Checks that text survives every encoding path unchanged
+///
+/// This trains a tree on the text and checks its codes are unique, then
+/// encodes and decodes it with `decode`, `decode_iter` and `encode_packed`,
+/// and finally compresses and decompresses it as a container. Empty text has
+/// no symbols to train a tree on, so only the container is checked. Any
+/// mismatch or error panics, which makes this suitable as a fuzz target.
+///
+/// This is only available with the `test-internals` feature and is not part
+/// of the stable API.
+pub fn assert_round_trip(text: &str) {
+	if !text.is_empty() {
+		let tree = HuffmanTree::new(text)
+			.expect("Failed to build Huffman tree");
+		assert!(tree.has_unique_codes());
+
+		let bits = tree.encode(text)
+			.expect("Failed to encode message");
+
+		assert_eq!(tree.decode(&bits).expect("Failed to decode message"), text);
+		assert_eq!(tree.decode_iter(&bits).collect::<anyhow::Result<String>>().expect("Failed to decode message"), text);
+		assert_eq!(tree.encode_packed(text).expect("Failed to encode message"), (bits.to_bytes(), bits.len()));
+	}
+
+	let container = compress(text)
+		.expect("Failed to compress message");
+	assert_eq!(decompress(&container, true).expect("Failed to decompress message"), text);
+}
+
+mod benchmarks {
+	#[allow(unused)]
+	use test::Bencher;
+
+	// Round trip text with edge cases
+	#[bench]
+	fn assert_round_trip(b: &mut Bencher) {
+		let text = std::fs::read_to_string("2022_fall-eecs660-pa2-input.txt")
+			.expect("Failed to read file");
+
+		for edge_case in ["", "a", "aaaa", "\0", "ab😀\u{10FFFF}", "\r\n\t\\"] {
+			super::assert_round_trip(edge_case);
+		}
+
+		b.iter(|| super::assert_round_trip(&text));
+	}
+}